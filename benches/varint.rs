@@ -0,0 +1,176 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use protobuf_lite::buffer::Reader;
+use protobuf_lite::error::DecodeError;
+use protobuf_lite::extract::extract_field;
+use protobuf_lite::protobuf::{decode_protobuf, Map, ProtoData};
+use protobuf_lite::varint::{read_uvarint, write_uvarint, write_varint};
+
+/// the byte loop `read_uvarint` used before the slice fast path, kept as the baseline
+fn read_uvarint_loop<T>(buf: &mut Reader<T>) -> Result<u64, DecodeError>
+where
+    T: AsRef<[u8]>,
+{
+    let mut x: u64 = 0;
+    let mut shift = 0;
+    if buf.is_end() {
+        return Err(DecodeError::EOF);
+    }
+    loop {
+        let b = buf.read_byte()? as u64;
+        x |= (b & 0x7F) << shift;
+        shift += 7;
+        if (b & 0x80) == 0 {
+            return Ok(x);
+        }
+        if shift >= 64 {
+            return Err(DecodeError::OverFlow64Bit);
+        }
+    }
+}
+
+/// small deterministic generator so every run decodes the same data
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        self.0 >> 33
+    }
+}
+
+/// concatenated varints of a typical mix: tags and small counters, ids, timestamps and
+/// negative int32/int64 values which are always 10 bytes long
+fn varint_mixes() -> Vec<(&'static str, Vec<u8>)> {
+    const COUNT: usize = 4096;
+    let mut rng = Lcg(42);
+    let mut mix = |f: &mut dyn FnMut(&mut Lcg, &mut Vec<u8>)| {
+        let mut buf = Vec::new();
+        for _ in 0..COUNT {
+            f(&mut rng, &mut buf);
+        }
+        buf
+    };
+    vec![
+        (
+            "small",
+            mix(&mut |rng, buf| write_uvarint(rng.next() % 128, buf).unwrap()),
+        ),
+        (
+            "ids",
+            mix(&mut |rng, buf| write_uvarint(rng.next() % 1_000_000, buf).unwrap()),
+        ),
+        (
+            "timestamps",
+            mix(&mut |rng, buf| {
+                write_uvarint(1_700_000_000_000 + rng.next() % 100_000_000, buf).unwrap()
+            }),
+        ),
+        (
+            "negative",
+            mix(&mut |rng, buf| write_uvarint(-((rng.next() % 1000) as i64) as u64, buf).unwrap()),
+        ),
+        (
+            "fields",
+            mix(&mut |rng, buf| {
+                let field = rng.next() % 20 + 1;
+                write_uvarint(field << 3, buf).unwrap();
+                match rng.next() % 4 {
+                    0 => write_uvarint(rng.next() % 2, buf).unwrap(),
+                    1 => write_uvarint(rng.next() % 300, buf).unwrap(),
+                    2 => write_varint(rng.next() as i64 - (1 << 30), buf).unwrap(),
+                    _ => write_uvarint(rng.next() << 20, buf).unwrap(),
+                }
+            }),
+        ),
+    ]
+}
+
+/// a message with the same field mix, decoded schemaless
+fn message() -> Vec<u8> {
+    let mut rng = Lcg(7);
+    let mut pb = Map::new();
+    for field in 1..=8 {
+        pb.insert(field, ProtoData::Varint(rng.next() % 300));
+    }
+    pb.insert(9, ProtoData::Varint(1_700_000_000_000));
+    pb.insert(10, ProtoData::Varint(-1i64 as u64));
+    pb.insert(
+        11,
+        (0..64)
+            .map(|_| ProtoData::Varint(rng.next() % 100_000))
+            .collect::<Vec<_>>()
+            .into(),
+    );
+    pb.insert(12, "name".into());
+    let mut outer = Map::new();
+    outer.insert(
+        1,
+        (0..32)
+            .map(|_| ProtoData::Message(pb.clone()))
+            .collect::<Vec<_>>()
+            .into(),
+    );
+    outer.encode().unwrap()
+}
+
+fn bench_read_uvarint(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read_uvarint");
+    for (name, data) in varint_mixes() {
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_with_input(BenchmarkId::new("loop", name), &data, |b, data| {
+            b.iter(|| {
+                let mut buf = Reader::new(data.as_slice());
+                let mut sum = 0u64;
+                while !buf.is_end() {
+                    sum = sum.wrapping_add(read_uvarint_loop(&mut buf).unwrap());
+                }
+                black_box(sum)
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("fast", name), &data, |b, data| {
+            b.iter(|| {
+                let mut buf = Reader::new(data.as_slice());
+                let mut sum = 0u64;
+                while !buf.is_end() {
+                    sum = sum.wrapping_add(read_uvarint(&mut buf).unwrap());
+                }
+                black_box(sum)
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_decode_protobuf(c: &mut Criterion) {
+    let data = message();
+    let mut group = c.benchmark_group("decode_protobuf");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function("message", |b| {
+        b.iter(|| decode_protobuf(black_box(data.as_slice())).unwrap())
+    });
+    group.finish();
+}
+
+fn bench_extract_field(c: &mut Criterion) {
+    let data = message();
+    let mut group = c.benchmark_group("extract_field");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function("first", |b| {
+        b.iter(|| extract_field(black_box(data.as_slice()), &[1, 1]).unwrap())
+    });
+    group.bench_function("missing", |b| {
+        b.iter(|| extract_field(black_box(data.as_slice()), &[2]).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_read_uvarint,
+    bench_decode_protobuf,
+    bench_extract_field
+);
+criterion_main!(benches);
@@ -0,0 +1,659 @@
+//! generate rust structs from `.proto` files, encoded and decoded with the protobuf-lite runtime
+//!
+//! example `build.rs`
+//! ```no_run
+//! protobuf_lite_build::compile_protos(&["proto/demo.proto"], &["proto"]).unwrap();
+//! ```
+//!
+//! and include the generated package in the crate
+//! ```ignore
+//! pub mod demo {
+//!     include!(concat!(env!("OUT_DIR"), "/demo.rs"));
+//! }
+//! ```
+//!
+//! every message becomes a struct implementing `protobuf_lite::message::ProtoMessage`.
+//! nested messages and enums live in a module named after the parent message, enums are
+//! generated as rust enums and stored as `i32` in the fields, oneof members become `Option`
+//! fields of which decoding keeps only the last one read and unknown fields are kept in
+//! `unknown_fields` and written back unchanged.
+use anyhow::Result;
+use protobuf_lite::schema::{
+    DescriptorPool, EnumDescriptor, FieldDescriptor, FieldType, Label, MessageDescriptor,
+};
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// code generator configuration
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    out_dir: Option<PathBuf>,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// set the output directory, defaults to `OUT_DIR`
+    pub fn out_dir<P>(&mut self, path: P) -> &mut Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.out_dir = Some(path.into());
+        self
+    }
+
+    /// generate one `<package>.rs` file per package of the files and their imports
+    pub fn compile_protos<P, I>(&self, protos: &[P], includes: &[I]) -> Result<()>
+    where
+        P: AsRef<Path>,
+        I: AsRef<Path>,
+    {
+        let out_dir = match &self.out_dir {
+            Some(dir) => dir.clone(),
+            None => PathBuf::from(env::var("OUT_DIR")?),
+        };
+        let includes = includes
+            .iter()
+            .map(|p| p.as_ref().to_path_buf())
+            .collect::<Vec<_>>();
+        let mut pool = DescriptorPool::new();
+        for proto in protos {
+            println!("cargo:rerun-if-changed={}", proto.as_ref().display());
+            pool.load(proto, &includes)?;
+        }
+        for (package, code) in generate(&pool) {
+            let name = match package.is_empty() {
+                true => "_".to_string(),
+                false => package,
+            };
+            fs::write(out_dir.join(format!("{}.rs", name)), code)?;
+        }
+        Ok(())
+    }
+}
+
+/// generate code with the default [`Config`]
+pub fn compile_protos<P, I>(protos: &[P], includes: &[I]) -> Result<()>
+where
+    P: AsRef<Path>,
+    I: AsRef<Path>,
+{
+    Config::new().compile_protos(protos, includes)
+}
+
+/// generate the code of every package in the pool, keyed by package name
+pub fn generate(pool: &DescriptorPool) -> BTreeMap<String, String> {
+    let mut generator = Generator {
+        pool,
+        paths: BTreeMap::new(),
+    };
+    for file in pool.files() {
+        let package = split_package(&file.package);
+        for msg in &file.messages {
+            generator.register_message(msg, &package, &[]);
+        }
+        for e in &file.enums {
+            generator
+                .paths
+                .insert(e.full_name.clone(), (package.clone(), Vec::new()));
+        }
+    }
+
+    let mut result: BTreeMap<String, String> = BTreeMap::new();
+    for file in pool.files() {
+        let package = split_package(&file.package);
+        let code = result
+            .entry(file.package.clone())
+            .or_insert_with(|| "// @generated by protobuf-lite-build, do not edit\n".to_string());
+        for msg in &file.messages {
+            code.push('\n');
+            generator.message(code, msg, &package, &[], 0);
+        }
+        for e in &file.enums {
+            code.push('\n');
+            generator.enumeration(code, e, 0);
+        }
+    }
+    result
+}
+
+fn split_package(package: &str) -> Vec<String> {
+    package
+        .split('.')
+        .filter(|s| !s.is_empty())
+        .map(escape)
+        .collect()
+}
+
+struct Generator<'a> {
+    pool: &'a DescriptorPool,
+    /// fully qualified name -> (package modules, parent message modules)
+    paths: BTreeMap<String, (Vec<String>, Vec<String>)>,
+}
+
+impl Generator<'_> {
+    fn register_message(
+        &mut self,
+        msg: &MessageDescriptor,
+        package: &[String],
+        parents: &[String],
+    ) {
+        self.paths
+            .insert(msg.full_name.clone(), (package.to_vec(), parents.to_vec()));
+        let mut nested = parents.to_vec();
+        nested.push(escape(&snake_case(&msg.name)));
+        for m in &msg.messages {
+            self.register_message(m, package, &nested);
+        }
+        for e in &msg.enums {
+            self.paths
+                .insert(e.full_name.clone(), (package.to_vec(), nested.clone()));
+        }
+    }
+
+    /// path of a message or enum relative to the module `package::parents`
+    fn type_path(&self, full_name: &str, package: &[String], parents: &[String]) -> String {
+        let (target_package, target_parents) = &self.paths[full_name];
+        let name = full_name.rsplit('.').next().unwrap_or(full_name);
+        let from = package.iter().chain(parents).collect::<Vec<_>>();
+        let to = target_package
+            .iter()
+            .chain(target_parents)
+            .collect::<Vec<_>>();
+        let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+        let mut path = String::new();
+        for _ in common..from.len() {
+            path.push_str("super::");
+        }
+        for m in &to[common..] {
+            path.push_str(m);
+            path.push_str("::");
+        }
+        path.push_str(&escape(name));
+        path
+    }
+
+    /// whether `target` contains `msg` through singular message fields
+    fn reaches(&self, target: &str, msg: &str, seen: &mut HashSet<String>) -> bool {
+        if target == msg {
+            return true;
+        }
+        if !seen.insert(target.to_string()) {
+            return false;
+        }
+        let Some(desc) = self.pool.message(target) else {
+            return false;
+        };
+        desc.fields.iter().any(|f| match &f.field_type {
+            FieldType::Message(name) if f.label != Label::Repeated => self.reaches(name, msg, seen),
+            _ => false,
+        })
+    }
+
+    fn kind(&self, ty: &FieldType, boxed: bool, package: &[String], parents: &[String]) -> String {
+        let name = match ty {
+            FieldType::Double => "Double".to_string(),
+            FieldType::Float => "Float".to_string(),
+            FieldType::Int64 => "Int64".to_string(),
+            FieldType::Uint64 => "Uint64".to_string(),
+            FieldType::Int32 | FieldType::Enum(_) => "Int32".to_string(),
+            FieldType::Fixed64 => "Fixed64".to_string(),
+            FieldType::Fixed32 => "Fixed32".to_string(),
+            FieldType::Bool => "Bool".to_string(),
+            FieldType::String => "String".to_string(),
+            FieldType::Bytes => "Bytes".to_string(),
+            FieldType::Uint32 => "Uint32".to_string(),
+            FieldType::Sfixed32 => "Sfixed32".to_string(),
+            FieldType::Sfixed64 => "Sfixed64".to_string(),
+            FieldType::Sint32 => "Sint32".to_string(),
+            FieldType::Sint64 => "Sint64".to_string(),
+            FieldType::Message(name) => {
+                let path = self.type_path(name, package, parents);
+                match boxed {
+                    true => format!("Message<::std::boxed::Box<{}>>", path),
+                    false => format!("Message<{}>", path),
+                }
+            }
+            FieldType::Named(_) | FieldType::Map(..) => unreachable!("unresolved field type"),
+        };
+        format!("::protobuf_lite::message::kind::{}", name)
+    }
+
+    fn rust_type(
+        &self,
+        ty: &FieldType,
+        boxed: bool,
+        package: &[String],
+        parents: &[String],
+    ) -> String {
+        match ty {
+            FieldType::Double => "f64".to_string(),
+            FieldType::Float => "f32".to_string(),
+            FieldType::Int64 | FieldType::Sint64 | FieldType::Sfixed64 => "i64".to_string(),
+            FieldType::Uint64 | FieldType::Fixed64 => "u64".to_string(),
+            FieldType::Int32 | FieldType::Sint32 | FieldType::Sfixed32 | FieldType::Enum(_) => {
+                "i32".to_string()
+            }
+            FieldType::Uint32 | FieldType::Fixed32 => "u32".to_string(),
+            FieldType::Bool => "bool".to_string(),
+            FieldType::String => "::std::string::String".to_string(),
+            FieldType::Bytes => "::std::vec::Vec<u8>".to_string(),
+            FieldType::Message(name) => {
+                let path = self.type_path(name, package, parents);
+                match boxed {
+                    true => format!("::std::boxed::Box<{}>", path),
+                    false => path,
+                }
+            }
+            FieldType::Named(_) | FieldType::Map(..) => unreachable!("unresolved field type"),
+        }
+    }
+
+    fn message(
+        &self,
+        code: &mut String,
+        msg: &MessageDescriptor,
+        package: &[String],
+        parents: &[String],
+        depth: usize,
+    ) {
+        let indent = "    ".repeat(depth);
+        let fields = msg
+            .fields
+            .iter()
+            .map(|f| self.field(msg, f, package, parents))
+            .collect::<Vec<_>>();
+
+        let _ = writeln!(
+            code,
+            "{}#[derive(Clone, Debug, Default, PartialEq)]",
+            indent
+        );
+        let _ = writeln!(code, "{}pub struct {} {{", indent, escape(&msg.name));
+        for f in &fields {
+            let _ = writeln!(code, "{}    pub {}: {},", indent, f.ident, f.ty);
+        }
+        let _ = writeln!(
+            code,
+            "{}    pub unknown_fields: ::protobuf_lite::message::UnknownFields,",
+            indent
+        );
+        let _ = writeln!(code, "{}}}", indent);
+        let _ = writeln!(code);
+
+        let _ = writeln!(
+            code,
+            "{}impl ::protobuf_lite::message::ProtoMessage for {} {{",
+            indent,
+            escape(&msg.name)
+        );
+        let _ = writeln!(
+            code,
+            "{i}    fn encode_to<W>(&self, buf: &mut W) -> ::core::result::Result<(), ::protobuf_lite::error::EncodeError>
+{i}    where
+{i}        W: ::std::io::Write,
+{i}    {{",
+            i = indent
+        );
+        for f in &fields {
+            let _ = writeln!(
+                code,
+                "{}        ::protobuf_lite::message::field::{}::<{}, W>({}, &self.{}, buf)?;",
+                indent, f.encode, f.kinds, f.number, f.ident
+            );
+        }
+        let _ = writeln!(
+            code,
+            "{i}        self.unknown_fields.encode_to(buf)
+{i}    }}
+
+{i}    fn merge_field<T>(
+{i}        &mut self,
+{i}        field: u64,
+{i}        wire_type: ::protobuf_lite::protobuf::WireType,
+{i}        buf: &mut ::protobuf_lite::buffer::Reader<T>,
+{i}    ) -> ::core::result::Result<(), ::protobuf_lite::error::DecodeError>
+{i}    where
+{i}        T: AsRef<[u8]>,
+{i}    {{
+{i}        match field {{",
+            i = indent
+        );
+        for (desc, f) in msg.fields.iter().zip(&fields) {
+            // the last member of a oneof on the wire wins, so reading one clears the others
+            let siblings = msg
+                .fields
+                .iter()
+                .zip(&fields)
+                .filter(|(d, _)| {
+                    d.oneof.is_some() && d.oneof == desc.oneof && d.number != desc.number
+                })
+                .collect::<Vec<_>>();
+            if siblings.is_empty() {
+                let _ = writeln!(
+                    code,
+                    "{}            {} => ::protobuf_lite::message::field::{}::<{}, T>(field, wire_type, &mut self.{}, buf),",
+                    indent, f.number, f.merge, f.kinds, f.ident
+                );
+                continue;
+            }
+            let _ = writeln!(code, "{}            {} => {{", indent, f.number);
+            for (_, sibling) in siblings {
+                let _ = writeln!(
+                    code,
+                    "{}                self.{} = None;",
+                    indent, sibling.ident
+                );
+            }
+            let _ = writeln!(
+                code,
+                "{i}                ::protobuf_lite::message::field::{}::<{}, T>(field, wire_type, &mut self.{}, buf)
+{i}            }}",
+                f.merge,
+                f.kinds,
+                f.ident,
+                i = indent
+            );
+        }
+        let _ = writeln!(
+            code,
+            "{i}            _ => self.unknown_fields.merge_field(field, wire_type, buf),
+{i}        }}
+{i}    }}
+
+{i}    fn into_map(self) -> ::protobuf_lite::protobuf::Map<u64, ::protobuf_lite::protobuf::ProtoData> {{
+{i}        let mut map = ::protobuf_lite::protobuf::Map::new();",
+            i = indent
+        );
+        for f in &fields {
+            let _ = writeln!(
+                code,
+                "{}        if let Some(v) = ::protobuf_lite::message::field::{}::<{}>(self.{}) {{
+{}            map.insert({}, v);
+{}        }}",
+                indent, f.to_data, f.kinds, f.ident, indent, f.number, indent
+            );
+        }
+        let _ = writeln!(
+            code,
+            "{i}        map.append(&mut self.unknown_fields.into_map());
+{i}        map
+{i}    }}
+
+{i}    fn from_map(
+{i}        mut map: ::protobuf_lite::protobuf::Map<u64, ::protobuf_lite::protobuf::ProtoData>,
+{i}    ) -> ::core::result::Result<Self, ::protobuf_lite::error::DecodeError> {{
+{i}        Ok(Self {{",
+            i = indent
+        );
+        for f in &fields {
+            let _ = writeln!(
+                code,
+                "{}            {}: ::protobuf_lite::message::field::{}::<{}>({}, map.remove(&{}))?,",
+                indent, f.ident, f.from_data, f.kinds, f.number, f.number
+            );
+        }
+        let _ = writeln!(
+            code,
+            "{i}            unknown_fields: ::protobuf_lite::message::UnknownFields::from_map(map)?,
+{i}        }})
+{i}    }}
+{i}}}",
+            i = indent
+        );
+
+        if msg.messages.is_empty() && msg.enums.is_empty() {
+            return;
+        }
+        let module = escape(&snake_case(&msg.name));
+        let mut nested = parents.to_vec();
+        nested.push(module.clone());
+        let _ = writeln!(code);
+        let _ = writeln!(code, "{}pub mod {} {{", indent, module);
+        for (i, m) in msg.messages.iter().enumerate() {
+            if i > 0 {
+                let _ = writeln!(code);
+            }
+            self.message(code, m, package, &nested, depth + 1);
+        }
+        for (i, e) in msg.enums.iter().enumerate() {
+            if i > 0 || !msg.messages.is_empty() {
+                let _ = writeln!(code);
+            }
+            self.enumeration(code, e, depth + 1);
+        }
+        let _ = writeln!(code, "{}}}", indent);
+    }
+
+    fn field(
+        &self,
+        msg: &MessageDescriptor,
+        f: &FieldDescriptor,
+        package: &[String],
+        parents: &[String],
+    ) -> GenField {
+        // types are referenced from the module the struct is declared in
+        let scope = parents;
+        let ident = escape(&snake_case(&f.name));
+        if let FieldType::Map(k, v) = &f.field_type {
+            return GenField {
+                ident,
+                number: f.number,
+                ty: format!(
+                    "::std::collections::BTreeMap<{}, {}>",
+                    self.rust_type(k, false, package, scope),
+                    self.rust_type(v, false, package, scope)
+                ),
+                kinds: format!(
+                    "{}, {}",
+                    self.kind(k, false, package, scope),
+                    self.kind(v, false, package, scope)
+                ),
+                encode: "encode_map",
+                merge: "merge_map",
+                to_data: "map_to_data",
+                from_data: "map_from_data",
+            };
+        }
+        let is_message = matches!(f.field_type, FieldType::Message(_));
+        let boxed = match &f.field_type {
+            FieldType::Message(name) if f.label != Label::Repeated => {
+                self.reaches(name, &msg.full_name, &mut HashSet::new())
+            }
+            _ => false,
+        };
+        let ty = self.rust_type(&f.field_type, boxed, package, scope);
+        let kinds = self.kind(&f.field_type, boxed, package, scope);
+        let (ty, encode, merge, to_data, from_data) = match f.label {
+            Label::Repeated => (
+                format!("::std::vec::Vec<{}>", ty),
+                "encode_repeated",
+                "merge_repeated",
+                "repeated_to_data",
+                "repeated_from_data",
+            ),
+            Label::Singular if !is_message => (ty, "encode", "merge", "to_data", "from_data"),
+            _ => (
+                format!("::std::option::Option<{}>", ty),
+                "encode_optional",
+                "merge_optional",
+                "optional_to_data",
+                "optional_from_data",
+            ),
+        };
+        GenField {
+            ident,
+            number: f.number,
+            ty,
+            kinds,
+            encode,
+            merge,
+            to_data,
+            from_data,
+        }
+    }
+
+    fn enumeration(&self, code: &mut String, e: &EnumDescriptor, depth: usize) {
+        let indent = "    ".repeat(depth);
+        let name = escape(&e.name);
+        let mut seen_values = HashSet::new();
+        let mut seen_names = HashSet::new();
+        let mut variants = Vec::new();
+        for (proto_name, value) in &e.values {
+            if !seen_values.insert(*value) {
+                continue;
+            }
+            let mut variant = variant_name(&e.name, proto_name);
+            while !seen_names.insert(variant.clone()) {
+                variant.push('_');
+            }
+            variants.push((variant, proto_name, *value));
+        }
+
+        let _ = writeln!(
+            code,
+            "{}#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]",
+            indent
+        );
+        let _ = writeln!(code, "{}#[repr(i32)]", indent);
+        let _ = writeln!(code, "{}pub enum {} {{", indent, name);
+        for (variant, _, value) in &variants {
+            let _ = writeln!(code, "{}    {} = {},", indent, variant, value);
+        }
+        let _ = writeln!(code, "{}}}", indent);
+        let _ = writeln!(code);
+        let _ = writeln!(code, "{}impl {} {{", indent, name);
+        let _ = writeln!(
+            code,
+            "{}    pub fn as_str_name(&self) -> &'static str {{",
+            indent
+        );
+        let _ = writeln!(code, "{}        match self {{", indent);
+        for (variant, proto_name, _) in &variants {
+            let _ = writeln!(
+                code,
+                "{}            {}::{} => {:?},",
+                indent, name, variant, proto_name
+            );
+        }
+        let _ = writeln!(code, "{}        }}", indent);
+        let _ = writeln!(code, "{}    }}", indent);
+        let _ = writeln!(code);
+        let _ = writeln!(
+            code,
+            "{}    pub fn from_str_name(value: &str) -> ::std::option::Option<Self> {{",
+            indent
+        );
+        let _ = writeln!(code, "{}        match value {{", indent);
+        for (variant, proto_name, _) in &variants {
+            let _ = writeln!(
+                code,
+                "{}            {:?} => Some({}::{}),",
+                indent, proto_name, name, variant
+            );
+        }
+        let _ = writeln!(code, "{}            _ => None,", indent);
+        let _ = writeln!(code, "{}        }}", indent);
+        let _ = writeln!(code, "{}    }}", indent);
+        let _ = writeln!(code, "{}}}", indent);
+        let _ = writeln!(code);
+        let _ = writeln!(
+            code,
+            "{}impl ::std::convert::TryFrom<i32> for {} {{",
+            indent, name
+        );
+        let _ = writeln!(code, "{}    type Error = i32;", indent);
+        let _ = writeln!(code);
+        let _ = writeln!(
+            code,
+            "{}    fn try_from(value: i32) -> ::std::result::Result<Self, i32> {{",
+            indent
+        );
+        let _ = writeln!(code, "{}        match value {{", indent);
+        for (variant, _, value) in &variants {
+            let _ = writeln!(
+                code,
+                "{}            {} => Ok({}::{}),",
+                indent, value, name, variant
+            );
+        }
+        let _ = writeln!(code, "{}            v => Err(v),", indent);
+        let _ = writeln!(code, "{}        }}", indent);
+        let _ = writeln!(code, "{}    }}", indent);
+        let _ = writeln!(code, "{}}}", indent);
+    }
+}
+
+struct GenField {
+    ident: String,
+    number: u64,
+    ty: String,
+    kinds: String,
+    encode: &'static str,
+    merge: &'static str,
+    to_data: &'static str,
+    from_data: &'static str,
+}
+
+fn snake_case(name: &str) -> String {
+    let mut result = String::new();
+    let chars = name.chars().collect::<Vec<_>>();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() {
+            let prev_lower =
+                i > 0 && (chars[i - 1].is_ascii_lowercase() || chars[i - 1].is_ascii_digit());
+            let next_lower = chars.get(i + 1).is_some_and(|c| c.is_ascii_lowercase());
+            let prev_upper = i > 0 && chars[i - 1].is_ascii_uppercase();
+            if !result.is_empty()
+                && !result.ends_with('_')
+                && (prev_lower || (prev_upper && next_lower))
+            {
+                result.push('_');
+            }
+            result.push(c.to_ascii_lowercase());
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+fn upper_camel_case(name: &str) -> String {
+    name.split('_')
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            let mut chars = s.chars();
+            let first = chars.next().unwrap().to_ascii_uppercase();
+            first.to_string() + &chars.as_str().to_ascii_lowercase()
+        })
+        .collect()
+}
+
+/// `ROLE_ADMIN` in enum `Role` becomes `Admin`
+fn variant_name(enum_name: &str, value: &str) -> String {
+    let prefix = snake_case(enum_name).to_ascii_uppercase() + "_";
+    let stripped = value.strip_prefix(&prefix).unwrap_or(value);
+    let name = upper_camel_case(stripped);
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        return upper_camel_case(value);
+    }
+    escape(&name)
+}
+
+fn escape(name: &str) -> String {
+    match name {
+        "self" | "super" | "crate" | "Self" => format!("{}_", name),
+        "as" | "break" | "const" | "continue" | "else" | "enum" | "extern" | "false" | "fn"
+        | "for" | "if" | "impl" | "in" | "let" | "loop" | "match" | "mod" | "move" | "mut"
+        | "pub" | "ref" | "return" | "static" | "struct" | "trait" | "true" | "type" | "unsafe"
+        | "use" | "where" | "while" | "async" | "await" | "dyn" | "abstract" | "become" | "box"
+        | "do" | "final" | "macro" | "override" | "priv" | "typeof" | "unsized" | "virtual"
+        | "yield" | "try" | "gen" => format!("r#{}", name),
+        _ => name.to_string(),
+    }
+}
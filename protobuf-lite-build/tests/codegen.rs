@@ -0,0 +1,102 @@
+use protobuf_lite::message::ProtoMessage;
+use protobuf_lite::protobuf::ProtoData;
+use protobuf_lite::schema::DescriptorPool;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+mod generated {
+    #[rustfmt::skip]
+    #[path = "demo.rs"]
+    pub mod demo;
+}
+
+use generated::demo::{user, Node, Role, User};
+
+fn generate_demo() -> String {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/protos");
+    let mut pool = DescriptorPool::new();
+    pool.load(dir.join("demo.proto"), &[dir]).unwrap();
+    protobuf_lite_build::generate(&pool).remove("demo").unwrap()
+}
+
+#[test]
+fn generated_code_is_up_to_date() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/generated/demo.rs");
+    let code = generate_demo();
+    if std::env::var("UPDATE_GENERATED").is_ok() {
+        fs::write(&path, &code).unwrap();
+    }
+    assert_eq!(fs::read_to_string(path).unwrap(), code);
+}
+
+#[test]
+fn round_trip() {
+    let user = User {
+        id: 42,
+        name: "alice".to_string(),
+        email: Some("alice@example.com".to_string()),
+        role: Role::Admin as i32,
+        tags: vec!["a".to_string(), "b".to_string()],
+        scores: vec![-1, 0, 1],
+        counters: BTreeMap::from([("x".to_string(), 1), ("y".to_string(), -2)]),
+        address: Some(user::Address {
+            street: "main".to_string(),
+            zip: 12345,
+            kind: user::address::Kind::Home as i32,
+            ..Default::default()
+        }),
+        history: vec![user::Address::default()],
+        phone: Some("555".to_string()),
+        ..Default::default()
+    };
+    let data = user.encode().unwrap();
+    assert_eq!(User::decode(&data).unwrap(), user);
+    assert_eq!(User::from_map(user.clone().into_map()).unwrap(), user);
+    assert_eq!(Role::try_from(1), Ok(Role::Admin));
+    assert_eq!(Role::Guest.as_str_name(), "ROLE_GUEST");
+    assert_eq!(Role::from_str_name("ROLE_UNKNOWN"), Some(Role::Unknown));
+    assert_eq!(
+        user::address::Kind::from_str_name(user::address::Kind::Work.as_str_name()),
+        Some(user::address::Kind::Work)
+    );
+
+    let node = Node {
+        value: "root".to_string(),
+        next: Some(Box::new(Node {
+            value: "next".to_string(),
+            ..Default::default()
+        })),
+        children: vec![Node::default()],
+        ..Default::default()
+    };
+    assert_eq!(Node::decode(node.encode().unwrap()).unwrap(), node);
+}
+
+#[test]
+fn unknown_fields_preserved() {
+    // field 1 = "x", unknown field 99 = varint 7, unknown field 100 = "abc"
+    let data = [
+        0x0a, 0x01, b'x', 0x98, 0x06, 0x07, 0xa2, 0x06, 0x03, b'a', b'b', b'c',
+    ];
+    let node = Node::decode(data).unwrap();
+    assert_eq!(node.value, "x");
+    assert_eq!(node.unknown_fields.get(99), Some(ProtoData::Varint(7)));
+    assert_eq!(node.encode().unwrap(), data);
+}
+
+#[test]
+fn oneof_last_member_wins() {
+    // phone = "555", then office = {street: "x"}
+    let data = [0x52, 0x03, b'5', b'5', b'5', 0x5a, 0x03, 0x0a, 0x01, b'x'];
+    let user = User::decode(data).unwrap();
+    assert_eq!(user.phone, None);
+    assert_eq!(user.office.unwrap().street, "x");
+
+    let mut data = data[5..].to_vec();
+    data.extend_from_slice(&[0x52, 0x03, b'5', b'5', b'5']);
+    let user = User::decode(&data).unwrap();
+    assert_eq!(user.phone.as_deref(), Some("555"));
+    assert_eq!(user.office, None);
+    assert_eq!(user.encode().unwrap(), data[5..]);
+}
@@ -0,0 +1,336 @@
+// @generated by protobuf-lite-build, do not edit
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct User {
+    pub id: u64,
+    pub name: ::std::string::String,
+    pub email: ::std::option::Option<::std::string::String>,
+    pub role: i32,
+    pub tags: ::std::vec::Vec<::std::string::String>,
+    pub scores: ::std::vec::Vec<i32>,
+    pub counters: ::std::collections::BTreeMap<::std::string::String, i64>,
+    pub address: ::std::option::Option<user::Address>,
+    pub history: ::std::vec::Vec<user::Address>,
+    pub phone: ::std::option::Option<::std::string::String>,
+    pub office: ::std::option::Option<user::Address>,
+    pub unknown_fields: ::protobuf_lite::message::UnknownFields,
+}
+
+impl ::protobuf_lite::message::ProtoMessage for User {
+    fn encode_to<W>(&self, buf: &mut W) -> ::core::result::Result<(), ::protobuf_lite::error::EncodeError>
+    where
+        W: ::std::io::Write,
+    {
+        ::protobuf_lite::message::field::encode::<::protobuf_lite::message::kind::Uint64, W>(1, &self.id, buf)?;
+        ::protobuf_lite::message::field::encode::<::protobuf_lite::message::kind::String, W>(2, &self.name, buf)?;
+        ::protobuf_lite::message::field::encode_optional::<::protobuf_lite::message::kind::String, W>(3, &self.email, buf)?;
+        ::protobuf_lite::message::field::encode::<::protobuf_lite::message::kind::Int32, W>(4, &self.role, buf)?;
+        ::protobuf_lite::message::field::encode_repeated::<::protobuf_lite::message::kind::String, W>(5, &self.tags, buf)?;
+        ::protobuf_lite::message::field::encode_repeated::<::protobuf_lite::message::kind::Sint32, W>(6, &self.scores, buf)?;
+        ::protobuf_lite::message::field::encode_map::<::protobuf_lite::message::kind::String, ::protobuf_lite::message::kind::Int64, W>(7, &self.counters, buf)?;
+        ::protobuf_lite::message::field::encode_optional::<::protobuf_lite::message::kind::Message<user::Address>, W>(8, &self.address, buf)?;
+        ::protobuf_lite::message::field::encode_repeated::<::protobuf_lite::message::kind::Message<user::Address>, W>(9, &self.history, buf)?;
+        ::protobuf_lite::message::field::encode_optional::<::protobuf_lite::message::kind::String, W>(10, &self.phone, buf)?;
+        ::protobuf_lite::message::field::encode_optional::<::protobuf_lite::message::kind::Message<user::Address>, W>(11, &self.office, buf)?;
+        self.unknown_fields.encode_to(buf)
+    }
+
+    fn merge_field<T>(
+        &mut self,
+        field: u64,
+        wire_type: ::protobuf_lite::protobuf::WireType,
+        buf: &mut ::protobuf_lite::buffer::Reader<T>,
+    ) -> ::core::result::Result<(), ::protobuf_lite::error::DecodeError>
+    where
+        T: AsRef<[u8]>,
+    {
+        match field {
+            1 => ::protobuf_lite::message::field::merge::<::protobuf_lite::message::kind::Uint64, T>(field, wire_type, &mut self.id, buf),
+            2 => ::protobuf_lite::message::field::merge::<::protobuf_lite::message::kind::String, T>(field, wire_type, &mut self.name, buf),
+            3 => ::protobuf_lite::message::field::merge_optional::<::protobuf_lite::message::kind::String, T>(field, wire_type, &mut self.email, buf),
+            4 => ::protobuf_lite::message::field::merge::<::protobuf_lite::message::kind::Int32, T>(field, wire_type, &mut self.role, buf),
+            5 => ::protobuf_lite::message::field::merge_repeated::<::protobuf_lite::message::kind::String, T>(field, wire_type, &mut self.tags, buf),
+            6 => ::protobuf_lite::message::field::merge_repeated::<::protobuf_lite::message::kind::Sint32, T>(field, wire_type, &mut self.scores, buf),
+            7 => ::protobuf_lite::message::field::merge_map::<::protobuf_lite::message::kind::String, ::protobuf_lite::message::kind::Int64, T>(field, wire_type, &mut self.counters, buf),
+            8 => ::protobuf_lite::message::field::merge_optional::<::protobuf_lite::message::kind::Message<user::Address>, T>(field, wire_type, &mut self.address, buf),
+            9 => ::protobuf_lite::message::field::merge_repeated::<::protobuf_lite::message::kind::Message<user::Address>, T>(field, wire_type, &mut self.history, buf),
+            10 => {
+                self.office = None;
+                ::protobuf_lite::message::field::merge_optional::<::protobuf_lite::message::kind::String, T>(field, wire_type, &mut self.phone, buf)
+            }
+            11 => {
+                self.phone = None;
+                ::protobuf_lite::message::field::merge_optional::<::protobuf_lite::message::kind::Message<user::Address>, T>(field, wire_type, &mut self.office, buf)
+            }
+            _ => self.unknown_fields.merge_field(field, wire_type, buf),
+        }
+    }
+
+    fn into_map(self) -> ::protobuf_lite::protobuf::Map<u64, ::protobuf_lite::protobuf::ProtoData> {
+        let mut map = ::protobuf_lite::protobuf::Map::new();
+        if let Some(v) = ::protobuf_lite::message::field::to_data::<::protobuf_lite::message::kind::Uint64>(self.id) {
+            map.insert(1, v);
+        }
+        if let Some(v) = ::protobuf_lite::message::field::to_data::<::protobuf_lite::message::kind::String>(self.name) {
+            map.insert(2, v);
+        }
+        if let Some(v) = ::protobuf_lite::message::field::optional_to_data::<::protobuf_lite::message::kind::String>(self.email) {
+            map.insert(3, v);
+        }
+        if let Some(v) = ::protobuf_lite::message::field::to_data::<::protobuf_lite::message::kind::Int32>(self.role) {
+            map.insert(4, v);
+        }
+        if let Some(v) = ::protobuf_lite::message::field::repeated_to_data::<::protobuf_lite::message::kind::String>(self.tags) {
+            map.insert(5, v);
+        }
+        if let Some(v) = ::protobuf_lite::message::field::repeated_to_data::<::protobuf_lite::message::kind::Sint32>(self.scores) {
+            map.insert(6, v);
+        }
+        if let Some(v) = ::protobuf_lite::message::field::map_to_data::<::protobuf_lite::message::kind::String, ::protobuf_lite::message::kind::Int64>(self.counters) {
+            map.insert(7, v);
+        }
+        if let Some(v) = ::protobuf_lite::message::field::optional_to_data::<::protobuf_lite::message::kind::Message<user::Address>>(self.address) {
+            map.insert(8, v);
+        }
+        if let Some(v) = ::protobuf_lite::message::field::repeated_to_data::<::protobuf_lite::message::kind::Message<user::Address>>(self.history) {
+            map.insert(9, v);
+        }
+        if let Some(v) = ::protobuf_lite::message::field::optional_to_data::<::protobuf_lite::message::kind::String>(self.phone) {
+            map.insert(10, v);
+        }
+        if let Some(v) = ::protobuf_lite::message::field::optional_to_data::<::protobuf_lite::message::kind::Message<user::Address>>(self.office) {
+            map.insert(11, v);
+        }
+        map.append(&mut self.unknown_fields.into_map());
+        map
+    }
+
+    fn from_map(
+        mut map: ::protobuf_lite::protobuf::Map<u64, ::protobuf_lite::protobuf::ProtoData>,
+    ) -> ::core::result::Result<Self, ::protobuf_lite::error::DecodeError> {
+        Ok(Self {
+            id: ::protobuf_lite::message::field::from_data::<::protobuf_lite::message::kind::Uint64>(1, map.remove(&1))?,
+            name: ::protobuf_lite::message::field::from_data::<::protobuf_lite::message::kind::String>(2, map.remove(&2))?,
+            email: ::protobuf_lite::message::field::optional_from_data::<::protobuf_lite::message::kind::String>(3, map.remove(&3))?,
+            role: ::protobuf_lite::message::field::from_data::<::protobuf_lite::message::kind::Int32>(4, map.remove(&4))?,
+            tags: ::protobuf_lite::message::field::repeated_from_data::<::protobuf_lite::message::kind::String>(5, map.remove(&5))?,
+            scores: ::protobuf_lite::message::field::repeated_from_data::<::protobuf_lite::message::kind::Sint32>(6, map.remove(&6))?,
+            counters: ::protobuf_lite::message::field::map_from_data::<::protobuf_lite::message::kind::String, ::protobuf_lite::message::kind::Int64>(7, map.remove(&7))?,
+            address: ::protobuf_lite::message::field::optional_from_data::<::protobuf_lite::message::kind::Message<user::Address>>(8, map.remove(&8))?,
+            history: ::protobuf_lite::message::field::repeated_from_data::<::protobuf_lite::message::kind::Message<user::Address>>(9, map.remove(&9))?,
+            phone: ::protobuf_lite::message::field::optional_from_data::<::protobuf_lite::message::kind::String>(10, map.remove(&10))?,
+            office: ::protobuf_lite::message::field::optional_from_data::<::protobuf_lite::message::kind::Message<user::Address>>(11, map.remove(&11))?,
+            unknown_fields: ::protobuf_lite::message::UnknownFields::from_map(map)?,
+        })
+    }
+}
+
+pub mod user {
+    #[derive(Clone, Debug, Default, PartialEq)]
+    pub struct Address {
+        pub street: ::std::string::String,
+        pub zip: u32,
+        pub kind: i32,
+        pub unknown_fields: ::protobuf_lite::message::UnknownFields,
+    }
+
+    impl ::protobuf_lite::message::ProtoMessage for Address {
+        fn encode_to<W>(&self, buf: &mut W) -> ::core::result::Result<(), ::protobuf_lite::error::EncodeError>
+        where
+            W: ::std::io::Write,
+        {
+            ::protobuf_lite::message::field::encode::<::protobuf_lite::message::kind::String, W>(1, &self.street, buf)?;
+            ::protobuf_lite::message::field::encode::<::protobuf_lite::message::kind::Fixed32, W>(2, &self.zip, buf)?;
+            ::protobuf_lite::message::field::encode::<::protobuf_lite::message::kind::Int32, W>(3, &self.kind, buf)?;
+            self.unknown_fields.encode_to(buf)
+        }
+
+        fn merge_field<T>(
+            &mut self,
+            field: u64,
+            wire_type: ::protobuf_lite::protobuf::WireType,
+            buf: &mut ::protobuf_lite::buffer::Reader<T>,
+        ) -> ::core::result::Result<(), ::protobuf_lite::error::DecodeError>
+        where
+            T: AsRef<[u8]>,
+        {
+            match field {
+                1 => ::protobuf_lite::message::field::merge::<::protobuf_lite::message::kind::String, T>(field, wire_type, &mut self.street, buf),
+                2 => ::protobuf_lite::message::field::merge::<::protobuf_lite::message::kind::Fixed32, T>(field, wire_type, &mut self.zip, buf),
+                3 => ::protobuf_lite::message::field::merge::<::protobuf_lite::message::kind::Int32, T>(field, wire_type, &mut self.kind, buf),
+                _ => self.unknown_fields.merge_field(field, wire_type, buf),
+            }
+        }
+
+        fn into_map(self) -> ::protobuf_lite::protobuf::Map<u64, ::protobuf_lite::protobuf::ProtoData> {
+            let mut map = ::protobuf_lite::protobuf::Map::new();
+            if let Some(v) = ::protobuf_lite::message::field::to_data::<::protobuf_lite::message::kind::String>(self.street) {
+                map.insert(1, v);
+            }
+            if let Some(v) = ::protobuf_lite::message::field::to_data::<::protobuf_lite::message::kind::Fixed32>(self.zip) {
+                map.insert(2, v);
+            }
+            if let Some(v) = ::protobuf_lite::message::field::to_data::<::protobuf_lite::message::kind::Int32>(self.kind) {
+                map.insert(3, v);
+            }
+            map.append(&mut self.unknown_fields.into_map());
+            map
+        }
+
+        fn from_map(
+            mut map: ::protobuf_lite::protobuf::Map<u64, ::protobuf_lite::protobuf::ProtoData>,
+        ) -> ::core::result::Result<Self, ::protobuf_lite::error::DecodeError> {
+            Ok(Self {
+                street: ::protobuf_lite::message::field::from_data::<::protobuf_lite::message::kind::String>(1, map.remove(&1))?,
+                zip: ::protobuf_lite::message::field::from_data::<::protobuf_lite::message::kind::Fixed32>(2, map.remove(&2))?,
+                kind: ::protobuf_lite::message::field::from_data::<::protobuf_lite::message::kind::Int32>(3, map.remove(&3))?,
+                unknown_fields: ::protobuf_lite::message::UnknownFields::from_map(map)?,
+            })
+        }
+    }
+
+    pub mod address {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        #[repr(i32)]
+        pub enum Kind {
+            Unknown = 0,
+            Home = 1,
+            Work = 2,
+        }
+
+        impl Kind {
+            pub fn as_str_name(&self) -> &'static str {
+                match self {
+                    Kind::Unknown => "KIND_UNKNOWN",
+                    Kind::Home => "KIND_HOME",
+                    Kind::Work => "KIND_WORK",
+                }
+            }
+
+            pub fn from_str_name(value: &str) -> ::std::option::Option<Self> {
+                match value {
+                    "KIND_UNKNOWN" => Some(Kind::Unknown),
+                    "KIND_HOME" => Some(Kind::Home),
+                    "KIND_WORK" => Some(Kind::Work),
+                    _ => None,
+                }
+            }
+        }
+
+        impl ::std::convert::TryFrom<i32> for Kind {
+            type Error = i32;
+
+            fn try_from(value: i32) -> ::std::result::Result<Self, i32> {
+                match value {
+                    0 => Ok(Kind::Unknown),
+                    1 => Ok(Kind::Home),
+                    2 => Ok(Kind::Work),
+                    v => Err(v),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Node {
+    pub value: ::std::string::String,
+    pub next: ::std::option::Option<::std::boxed::Box<Node>>,
+    pub children: ::std::vec::Vec<Node>,
+    pub unknown_fields: ::protobuf_lite::message::UnknownFields,
+}
+
+impl ::protobuf_lite::message::ProtoMessage for Node {
+    fn encode_to<W>(&self, buf: &mut W) -> ::core::result::Result<(), ::protobuf_lite::error::EncodeError>
+    where
+        W: ::std::io::Write,
+    {
+        ::protobuf_lite::message::field::encode::<::protobuf_lite::message::kind::String, W>(1, &self.value, buf)?;
+        ::protobuf_lite::message::field::encode_optional::<::protobuf_lite::message::kind::Message<::std::boxed::Box<Node>>, W>(2, &self.next, buf)?;
+        ::protobuf_lite::message::field::encode_repeated::<::protobuf_lite::message::kind::Message<Node>, W>(3, &self.children, buf)?;
+        self.unknown_fields.encode_to(buf)
+    }
+
+    fn merge_field<T>(
+        &mut self,
+        field: u64,
+        wire_type: ::protobuf_lite::protobuf::WireType,
+        buf: &mut ::protobuf_lite::buffer::Reader<T>,
+    ) -> ::core::result::Result<(), ::protobuf_lite::error::DecodeError>
+    where
+        T: AsRef<[u8]>,
+    {
+        match field {
+            1 => ::protobuf_lite::message::field::merge::<::protobuf_lite::message::kind::String, T>(field, wire_type, &mut self.value, buf),
+            2 => ::protobuf_lite::message::field::merge_optional::<::protobuf_lite::message::kind::Message<::std::boxed::Box<Node>>, T>(field, wire_type, &mut self.next, buf),
+            3 => ::protobuf_lite::message::field::merge_repeated::<::protobuf_lite::message::kind::Message<Node>, T>(field, wire_type, &mut self.children, buf),
+            _ => self.unknown_fields.merge_field(field, wire_type, buf),
+        }
+    }
+
+    fn into_map(self) -> ::protobuf_lite::protobuf::Map<u64, ::protobuf_lite::protobuf::ProtoData> {
+        let mut map = ::protobuf_lite::protobuf::Map::new();
+        if let Some(v) = ::protobuf_lite::message::field::to_data::<::protobuf_lite::message::kind::String>(self.value) {
+            map.insert(1, v);
+        }
+        if let Some(v) = ::protobuf_lite::message::field::optional_to_data::<::protobuf_lite::message::kind::Message<::std::boxed::Box<Node>>>(self.next) {
+            map.insert(2, v);
+        }
+        if let Some(v) = ::protobuf_lite::message::field::repeated_to_data::<::protobuf_lite::message::kind::Message<Node>>(self.children) {
+            map.insert(3, v);
+        }
+        map.append(&mut self.unknown_fields.into_map());
+        map
+    }
+
+    fn from_map(
+        mut map: ::protobuf_lite::protobuf::Map<u64, ::protobuf_lite::protobuf::ProtoData>,
+    ) -> ::core::result::Result<Self, ::protobuf_lite::error::DecodeError> {
+        Ok(Self {
+            value: ::protobuf_lite::message::field::from_data::<::protobuf_lite::message::kind::String>(1, map.remove(&1))?,
+            next: ::protobuf_lite::message::field::optional_from_data::<::protobuf_lite::message::kind::Message<::std::boxed::Box<Node>>>(2, map.remove(&2))?,
+            children: ::protobuf_lite::message::field::repeated_from_data::<::protobuf_lite::message::kind::Message<Node>>(3, map.remove(&3))?,
+            unknown_fields: ::protobuf_lite::message::UnknownFields::from_map(map)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(i32)]
+pub enum Role {
+    Unknown = 0,
+    Admin = 1,
+    Guest = 2,
+}
+
+impl Role {
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Role::Unknown => "ROLE_UNKNOWN",
+            Role::Admin => "ROLE_ADMIN",
+            Role::Guest => "ROLE_GUEST",
+        }
+    }
+
+    pub fn from_str_name(value: &str) -> ::std::option::Option<Self> {
+        match value {
+            "ROLE_UNKNOWN" => Some(Role::Unknown),
+            "ROLE_ADMIN" => Some(Role::Admin),
+            "ROLE_GUEST" => Some(Role::Guest),
+            _ => None,
+        }
+    }
+}
+
+impl ::std::convert::TryFrom<i32> for Role {
+    type Error = i32;
+
+    fn try_from(value: i32) -> ::std::result::Result<Self, i32> {
+        match value {
+            0 => Ok(Role::Unknown),
+            1 => Ok(Role::Admin),
+            2 => Ok(Role::Guest),
+            v => Err(v),
+        }
+    }
+}
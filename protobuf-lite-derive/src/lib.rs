@@ -0,0 +1,346 @@
+//! `#[derive(ProtoMessage)]` for protobuf-lite
+//!
+//! every field needs a `#[proto(tag = N)]` attribute, the protobuf type is inferred from the
+//! rust type or given with `kind`:
+//!
+//! ```ignore
+//! #[derive(ProtoMessage, Default)]
+//! struct Request {
+//!     #[proto(tag = 1)]
+//!     id: u64,
+//!     #[proto(tag = 2, kind = "sint64")]
+//!     offset: i64,
+//!     #[proto(tag = 3)]
+//!     tags: Vec<String>,
+//!     #[proto(skip)]
+//!     cache: Option<String>,
+//!     #[proto(unknown)]
+//!     unknown_fields: UnknownFields,
+//! }
+//! ```
+//!
+//! `Option<T>` fields are optional, `Vec<T>` fields are repeated except `Vec<u8>` which is
+//! `bytes`. types without a scalar mapping are embedded messages implementing `ProtoMessage`.
+//! a `#[proto(unknown)]` field of type `UnknownFields` keeps fields without a tag in the struct,
+//! otherwise they are skipped.
+use proc_macro2::TokenStream;
+use quote::quote;
+use std::collections::HashSet;
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, Data, DeriveInput, Error, Fields, GenericArgument, LitInt, LitStr,
+    PathArguments, Type,
+};
+
+#[proc_macro_derive(ProtoMessage, attributes(proto))]
+pub fn derive_proto_message(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+enum Label {
+    Single,
+    Optional,
+    Repeated,
+}
+
+struct ProtoField {
+    ident: syn::Ident,
+    tag: u64,
+    label: Label,
+    kind: TokenStream,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    input.span(),
+                    "ProtoMessage requires a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                input.span(),
+                "ProtoMessage can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut proto_fields = Vec::new();
+    let mut skipped = Vec::new();
+    let mut unknown: Option<syn::Ident> = None;
+    let mut tags = HashSet::new();
+    for field in fields {
+        let ident = field.ident.clone().unwrap();
+        let mut tag = None;
+        let mut kind = None;
+        let mut skip = false;
+        let mut is_unknown = false;
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("proto")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("tag") {
+                    let lit: LitInt = meta.value()?.parse()?;
+                    tag = Some((lit.base10_parse::<u64>()?, lit.span()));
+                } else if meta.path.is_ident("kind") {
+                    kind = Some(meta.value()?.parse::<LitStr>()?);
+                } else if meta.path.is_ident("skip") {
+                    skip = true;
+                } else if meta.path.is_ident("unknown") {
+                    is_unknown = true;
+                } else {
+                    return Err(meta.error("expected `tag`, `kind`, `skip` or `unknown`"));
+                }
+                Ok(())
+            })?;
+        }
+        if skip {
+            skipped.push(ident);
+            continue;
+        }
+        if is_unknown {
+            if unknown.is_some() {
+                return Err(Error::new(
+                    field.span(),
+                    "only one field can be marked `#[proto(unknown)]`",
+                ));
+            }
+            unknown = Some(ident);
+            continue;
+        }
+        let Some((tag, span)) = tag else {
+            return Err(Error::new(
+                field.span(),
+                "missing `#[proto(tag = N)]` attribute",
+            ));
+        };
+        if tag == 0 || tag > (1 << 29) - 1 {
+            return Err(Error::new(span, "field number must be in 1..=536870911"));
+        }
+        if !tags.insert(tag) {
+            return Err(Error::new(span, format!("duplicate field number {}", tag)));
+        }
+        let (label, kind) = resolve(&field.ty, kind.as_ref())?;
+        proto_fields.push(ProtoField {
+            ident,
+            tag,
+            label,
+            kind,
+        });
+    }
+
+    let encode = proto_fields.iter().map(|f| {
+        let (ident, tag, kind) = (&f.ident, f.tag, &f.kind);
+        let func = match f.label {
+            Label::Single => quote!(encode),
+            Label::Optional => quote!(encode_optional),
+            Label::Repeated => quote!(encode_repeated),
+        };
+        quote! {
+            ::protobuf_lite::message::field::#func::<#kind, __W>(#tag, &self.#ident, buf)?;
+        }
+    });
+    let merge = proto_fields.iter().map(|f| {
+        let (ident, tag, kind) = (&f.ident, f.tag, &f.kind);
+        let func = match f.label {
+            Label::Single => quote!(merge),
+            Label::Optional => quote!(merge_optional),
+            Label::Repeated => quote!(merge_repeated),
+        };
+        quote! {
+            #tag => ::protobuf_lite::message::field::#func::<#kind, __T>(
+                field, wire_type, &mut self.#ident, buf
+            ),
+        }
+    });
+    let to_data = proto_fields.iter().map(|f| {
+        let (ident, tag, kind) = (&f.ident, f.tag, &f.kind);
+        let func = match f.label {
+            Label::Single => quote!(to_data),
+            Label::Optional => quote!(optional_to_data),
+            Label::Repeated => quote!(repeated_to_data),
+        };
+        quote! {
+            if let Some(v) = ::protobuf_lite::message::field::#func::<#kind>(self.#ident) {
+                map.insert(#tag, v);
+            }
+        }
+    });
+    let from_data = proto_fields.iter().map(|f| {
+        let (ident, tag, kind) = (&f.ident, f.tag, &f.kind);
+        let func = match f.label {
+            Label::Single => quote!(from_data),
+            Label::Optional => quote!(optional_from_data),
+            Label::Repeated => quote!(repeated_from_data),
+        };
+        quote! {
+            #ident: ::protobuf_lite::message::field::#func::<#kind>(#tag, map.remove(&#tag))?,
+        }
+    });
+
+    let encode_unknown = unknown.iter().map(|ident| {
+        quote! {
+            ::protobuf_lite::message::UnknownFields::encode_to(&self.#ident, buf)?;
+        }
+    });
+    let merge_unknown = match &unknown {
+        Some(ident) => quote! {
+            ::protobuf_lite::message::UnknownFields::merge_field(&mut self.#ident, field, wire_type, buf)
+        },
+        None => quote!(::protobuf_lite::message::field::skip(wire_type, buf)),
+    };
+    let unknown_to_data = unknown.iter().map(|ident| {
+        quote! {
+            map.append(&mut ::protobuf_lite::message::UnknownFields::into_map(self.#ident));
+        }
+    });
+    let unknown_from_data = unknown.iter().map(|ident| {
+        quote! {
+            #ident: ::protobuf_lite::message::UnknownFields::from_map(map)?,
+        }
+    });
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::protobuf_lite::message::ProtoMessage for #name #ty_generics #where_clause {
+            fn encode_to<__W>(&self, buf: &mut __W) -> ::core::result::Result<(), ::protobuf_lite::error::EncodeError>
+            where
+                __W: ::std::io::Write,
+            {
+                #(#encode)*
+                #(#encode_unknown)*
+                Ok(())
+            }
+
+            fn merge_field<__T>(
+                &mut self,
+                field: u64,
+                wire_type: ::protobuf_lite::protobuf::WireType,
+                buf: &mut ::protobuf_lite::buffer::Reader<__T>,
+            ) -> ::core::result::Result<(), ::protobuf_lite::error::DecodeError>
+            where
+                __T: ::core::convert::AsRef<[u8]>,
+            {
+                match field {
+                    #(#merge)*
+                    _ => #merge_unknown,
+                }
+            }
+
+            #[allow(unused_mut)]
+            fn into_map(
+                self,
+            ) -> ::protobuf_lite::protobuf::Map<u64, ::protobuf_lite::protobuf::ProtoData> {
+                let mut map = ::protobuf_lite::protobuf::Map::new();
+                #(#to_data)*
+                #(#unknown_to_data)*
+                map
+            }
+
+            #[allow(unused_mut, unused_variables)]
+            fn from_map(
+                mut map: ::protobuf_lite::protobuf::Map<u64, ::protobuf_lite::protobuf::ProtoData>,
+            ) -> ::core::result::Result<Self, ::protobuf_lite::error::DecodeError> {
+                Ok(Self {
+                    #(#from_data)*
+                    #(#skipped: ::core::default::Default::default(),)*
+                    #(#unknown_from_data)*
+                })
+            }
+        }
+    })
+}
+
+/// gets `T` from `Wrapper<T>`
+fn inner_type<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(ty) if args.args.len() == 1 => Some(ty),
+        _ => None,
+    }
+}
+
+fn is_type(ty: &Type, name: &str) -> bool {
+    matches!(ty, Type::Path(path) if path.qself.is_none() && path.path.is_ident(name))
+}
+
+fn is_bytes(ty: &Type) -> bool {
+    inner_type(ty, "Vec").is_some_and(|inner| is_type(inner, "u8"))
+}
+
+fn resolve(ty: &Type, kind: Option<&LitStr>) -> syn::Result<(Label, TokenStream)> {
+    let (label, base) = if is_bytes(ty) {
+        (Label::Single, ty)
+    } else if let Some(inner) = inner_type(ty, "Option") {
+        (Label::Optional, inner)
+    } else if let Some(inner) = inner_type(ty, "Vec") {
+        (Label::Repeated, inner)
+    } else {
+        (Label::Single, ty)
+    };
+    let kind = match kind {
+        Some(kind) => kind_tokens(&kind.value(), base)
+            .ok_or_else(|| Error::new(kind.span(), format!("unknown kind `{}`", kind.value())))?,
+        None => kind_tokens(infer_kind(base), base).unwrap(),
+    };
+    Ok((label, kind))
+}
+
+fn infer_kind(ty: &Type) -> &'static str {
+    if is_bytes(ty) {
+        return "bytes";
+    }
+    for (name, kind) in [
+        ("i32", "int32"),
+        ("i64", "int64"),
+        ("u32", "uint32"),
+        ("u64", "uint64"),
+        ("bool", "bool"),
+        ("f32", "float"),
+        ("f64", "double"),
+        ("String", "string"),
+    ] {
+        if is_type(ty, name) {
+            return kind;
+        }
+    }
+    "message"
+}
+
+fn kind_tokens(kind: &str, base: &Type) -> Option<TokenStream> {
+    let name = match kind {
+        "int32" | "enum" => quote!(Int32),
+        "int64" => quote!(Int64),
+        "uint32" => quote!(Uint32),
+        "uint64" => quote!(Uint64),
+        "sint32" => quote!(Sint32),
+        "sint64" => quote!(Sint64),
+        "bool" => quote!(Bool),
+        "fixed32" => quote!(Fixed32),
+        "fixed64" => quote!(Fixed64),
+        "sfixed32" => quote!(Sfixed32),
+        "sfixed64" => quote!(Sfixed64),
+        "float" => quote!(Float),
+        "double" => quote!(Double),
+        "string" => quote!(String),
+        "bytes" => quote!(Bytes),
+        "message" => quote!(Message<#base>),
+        _ => return None,
+    };
+    Some(quote!(::protobuf_lite::message::kind::#name))
+}
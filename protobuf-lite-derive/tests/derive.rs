@@ -0,0 +1,180 @@
+use protobuf_lite::message::{ProtoMessage, UnknownFields};
+use protobuf_lite::protobuf::{decode_protobuf, Map, ProtoData};
+
+#[derive(protobuf_lite_derive::ProtoMessage, Default, Debug, PartialEq, Clone)]
+struct Inner {
+    #[proto(tag = 1)]
+    name: String,
+    #[proto(tag = 2, kind = "fixed32")]
+    code: u32,
+}
+
+#[derive(protobuf_lite_derive::ProtoMessage, Default, Debug, PartialEq)]
+struct Outer {
+    #[proto(tag = 1)]
+    id: u64,
+    #[proto(tag = 2, kind = "sint64")]
+    offset: i64,
+    #[proto(tag = 3)]
+    tags: Vec<String>,
+    #[proto(tag = 4)]
+    numbers: Vec<i32>,
+    #[proto(tag = 5)]
+    inner: Inner,
+    #[proto(tag = 6)]
+    children: Vec<Inner>,
+    #[proto(tag = 7)]
+    ratio: Option<f64>,
+    #[proto(tag = 8)]
+    raw: Vec<u8>,
+    #[proto(tag = 9)]
+    enabled: bool,
+    #[proto(skip)]
+    cache: Option<String>,
+}
+
+fn sample() -> Outer {
+    Outer {
+        id: 114514,
+        offset: -1919810,
+        tags: vec!["a".into(), "b".into()],
+        numbers: vec![1, -2, 300],
+        inner: Inner {
+            name: "inner".into(),
+            code: 7,
+        },
+        children: vec![
+            Inner {
+                name: "x".into(),
+                code: 1,
+            },
+            Inner {
+                name: "y".into(),
+                code: 2,
+            },
+        ],
+        ratio: Some(0.5),
+        raw: vec![0xde, 0xad, 0xbe, 0xef],
+        enabled: true,
+        cache: None,
+    }
+}
+
+#[test]
+fn encode_decode() {
+    let msg = sample();
+    let data = msg.encode().unwrap();
+    assert_eq!(Outer::decode(&data).unwrap(), msg);
+
+    // default scalars are not written, singular messages always are
+    assert_eq!(Outer::default().encode().unwrap(), vec![0x2a, 0x00]);
+
+    // unknown fields are skipped
+    let mut extended = data.clone();
+    extended.extend_from_slice(&[0xf8, 0x01, 0x2a]);
+    assert_eq!(Outer::decode(&extended).unwrap(), msg);
+}
+
+#[test]
+fn map_conversion() {
+    let msg = sample();
+    let map = msg.into_map();
+    assert_eq!(map.get(&1), Some(&ProtoData::Varint(114514)));
+    assert_eq!(map.get(&2), Some(&ProtoData::Varint(3839619)));
+    assert_eq!(
+        map.get(&3),
+        Some(&ProtoData::Repeated(vec!["a".into(), "b".into()]))
+    );
+    assert_eq!(Outer::from_map(map).unwrap(), sample());
+
+    let mut inner = Map::new();
+    inner.insert(1, "inner".into());
+    inner.insert(2, ProtoData::Fix32(7));
+    assert_eq!(Inner::from_map(inner.clone()).unwrap(), sample().inner);
+
+    // the dynamic decoder sees the packed numbers as bytes
+    let data = sample().encode().unwrap();
+    let ProtoData::Message(map) = decode_protobuf(&data).unwrap() else {
+        panic!("not a message")
+    };
+    assert_eq!(Outer::from_map(map).unwrap().numbers, vec![1, -2, 300]);
+
+    let mut invalid = Map::new();
+    invalid.insert(1, "not a number".into());
+    assert!(Outer::from_map(invalid).is_err());
+}
+
+#[derive(protobuf_lite_derive::ProtoMessage, Default, Debug, PartialEq)]
+struct OldOuter {
+    #[proto(tag = 1)]
+    id: u64,
+    #[proto(unknown)]
+    unknown_fields: UnknownFields,
+}
+
+#[test]
+fn unknown_fields() {
+    let data = sample().encode().unwrap();
+    let old = OldOuter::decode(&data).unwrap();
+    assert_eq!(old.id, 114514);
+    assert_eq!(old.unknown_fields.get(9), Some(ProtoData::Varint(1)));
+    assert_eq!(
+        old.unknown_fields.get(8),
+        Some(ProtoData::Bytes(vec![0xde, 0xad, 0xbe, 0xef]))
+    );
+
+    // re-encoding an older version keeps the fields it does not know
+    assert_eq!(old.encode().unwrap(), data);
+    assert_eq!(Outer::decode(old.encode().unwrap()).unwrap(), sample());
+
+    let map = old.into_map();
+    assert_eq!(map.len(), 9);
+    assert_eq!(
+        OldOuter::from_map(map)
+            .unwrap()
+            .unknown_fields
+            .to_map()
+            .len(),
+        8
+    );
+
+    // overlong varints, unknown fields around a known one out of number order and a group
+    // are written back byte for byte
+    let data = [
+        0x98, 0x86, 0x00, 0x87, 0x80, 0x00, // field 99: overlong tag and value
+        0x08, 0x01, // field 1
+        0x10, 0x02, // field 2
+        0x1b, 0x08, 0x05, 0x1c, // field 3: group {1: 5}
+    ];
+    let old = OldOuter::decode(data).unwrap();
+    assert_eq!(old.id, 1);
+    assert_eq!(old.unknown_fields.get(99), Some(ProtoData::Varint(7)));
+    assert_eq!(old.unknown_fields.get(3), None);
+    let mut expected = data[6..8].to_vec();
+    expected.extend_from_slice(&data[..6]);
+    expected.extend_from_slice(&data[8..]);
+    assert_eq!(old.encode().unwrap(), expected);
+    assert_eq!(
+        old.unknown_fields.as_bytes(),
+        [&data[..6], &data[8..]].concat()
+    );
+
+    // a group has to end with the tag of its own field
+    assert!(OldOuter::decode([0x1b, 0x08, 0x05, 0x24]).is_err());
+}
+
+#[test]
+fn merge_singular_message() {
+    // field 5 twice: the second occurrence only sets `code`, `name` has to survive
+    let mut data = vec![0x2a, 0x07, 0x0a, 0x05];
+    data.extend_from_slice(b"inner");
+    data.extend_from_slice(&[0x2a, 0x05, 0x15, 0x07, 0x00, 0x00, 0x00]);
+    let msg = Outer::decode(&data).unwrap();
+    assert_eq!(
+        msg.inner,
+        Inner {
+            name: "inner".into(),
+            code: 7,
+        }
+    );
+}
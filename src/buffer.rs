@@ -1,5 +1,7 @@
-use anyhow::Result;
-use std::io::{Error, ErrorKind};
+use crate::error::{DecodeError, EncodeError};
+use crate::protobuf::{ProtoData, WireType};
+use crate::varint::{encode_uvarint, write_uvarint};
+use alloc::vec::Vec;
 
 /// buffer reader
 ///
@@ -15,6 +17,9 @@ pub struct Reader<T> {
     buf: T,
     pos: usize,
     remain: usize,
+    // absolute offset of the first byte, non-zero for sub-readers created by `limit`
+    offset: usize,
+    mark: usize,
 }
 
 impl<T> Reader<T>
@@ -37,14 +42,95 @@ where
             buf: data,
             pos: 0,
             remain: length,
+            offset: 0,
+            mark: 0,
         }
     }
 
-    /// reset reader position
+    /// create a buffer reader whose positions start at `offset` instead of zero
+    pub fn with_offset(data: T, offset: usize) -> Self {
+        let mut reader = Self::new(data);
+        reader.offset = offset;
+        reader
+    }
+
+    /// gets the absolute position of the next byte
+    ///
+    /// positions of a sub-reader created by [`Reader::limit`] continue the ones of its parent
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.offset + self.pos
+    }
+
+    /// gets the absolute position of the first byte
+    #[inline]
+    pub fn start(&self) -> usize {
+        self.offset
+    }
+
+    /// gets the absolute position behind the last byte
+    #[inline]
+    pub fn end(&self) -> usize {
+        self.offset + self.buf.as_ref().len()
+    }
+
+    /// move to an absolute position between [`Reader::start`] and [`Reader::end`]
+    #[inline]
+    pub fn seek(&mut self, position: usize) -> Result<(), DecodeError> {
+        if position < self.start() || position > self.end() {
+            return Err(DecodeError::UnexpectedEof);
+        }
+        self.pos = position - self.offset;
+        self.remain = self.buf.as_ref().len() - self.pos;
+        Ok(())
+    }
+
+    /// remember the current position for [`Reader::rewind`]
+    #[inline]
+    pub fn mark(&mut self) {
+        self.mark = self.pos;
+    }
+
+    /// go back to the position saved by the last [`Reader::mark`], or the start if there is none
+    #[inline]
+    pub fn rewind(&mut self) {
+        self.pos = self.mark;
+        self.remain = self.buf.as_ref().len() - self.pos;
+    }
+
+    /// borrow the next `n` bytes as a sub-reader and skip them in this one
+    ///
+    /// the sub-reader reports absolute positions, so offsets inside nested messages still
+    /// point into the outermost buffer
+    ///
+    /// example
+    /// ```
+    /// use protobuf_lite::buffer::Reader;
+    /// fn main() {
+    ///     let data: Vec<u8> = vec![1, 2, 3, 4, 5];
+    ///     let mut reader = Reader::new(data.as_slice());
+    ///     reader.skip(1).unwrap();
+    ///     let mut sub = reader.limit(3).unwrap();
+    ///     assert_eq!(sub.position(), 1);
+    ///     assert_eq!(sub.read_byte().unwrap(), 2);
+    ///     assert_eq!(sub.position(), 2);
+    ///     assert_eq!(sub.remaining(), 2);
+    ///     assert_eq!(reader.position(), 4);
+    /// }
+    /// ```
+    #[inline]
+    pub fn limit(&mut self, n: usize) -> Result<Reader<&[u8]>, DecodeError> {
+        let offset = self.position();
+        let data = self.read_bytes(n)?;
+        Ok(Reader::with_offset(data, offset))
+    }
+
+    /// reset reader position, the mark is cleared
     #[inline]
     pub fn reset(&mut self) {
         self.pos = 0;
         self.remain = self.buf.as_ref().len();
+        self.mark = 0;
     }
 
     /// gets remaining length of the buffer
@@ -55,26 +141,48 @@ where
 
     /// skip specified byte of data
     #[inline]
-    pub fn skip(&mut self, n: usize) -> Result<()> {
+    pub fn skip(&mut self, n: usize) -> Result<(), DecodeError> {
         if self.remain < n {
-            return Err(Error::new(ErrorKind::UnexpectedEof, "unexpected EOF").into());
+            return Err(DecodeError::UnexpectedEof);
         }
         self.pos += n;
         self.remain -= n;
         Ok(())
     }
 
+    /// gets the unread part of the buffer without consuming it
+    #[inline]
+    pub fn remaining_slice(&self) -> &[u8] {
+        &self.buf.as_ref()[self.pos..]
+    }
+
+    /// gets the part of the buffer which has already been read
+    #[cfg(feature = "std")]
+    #[inline]
+    pub(crate) fn consumed_slice(&self) -> &[u8] {
+        &self.buf.as_ref()[..self.pos]
+    }
+
     /// check if the buffer pointer is at the end
     #[inline]
     pub fn is_end(&self) -> bool {
         self.remain == 0
     }
 
+    /// gets the next byte without consuming it
+    #[inline]
+    pub fn peek_byte(&self) -> Result<u8, DecodeError> {
+        if self.remain < 1 {
+            return Err(DecodeError::UnexpectedEof);
+        }
+        Ok(self.buf.as_ref()[self.pos])
+    }
+
     /// read 1 byte of data
     #[inline]
-    pub fn read_byte(&mut self) -> Result<u8> {
+    pub fn read_byte(&mut self) -> Result<u8, DecodeError> {
         if self.remain < 1 {
-            return Err(Error::new(ErrorKind::UnexpectedEof, "unexpected EOF").into());
+            return Err(DecodeError::UnexpectedEof);
         }
         let b = self.buf.as_ref()[self.pos];
         self.pos += 1;
@@ -84,9 +192,9 @@ where
 
     /// read specified byte of data
     #[inline]
-    pub fn read_bytes(&mut self, n: usize) -> Result<&[u8]> {
+    pub fn read_bytes(&mut self, n: usize) -> Result<&[u8], DecodeError> {
         if self.remain < n {
-            return Err(Error::new(ErrorKind::UnexpectedEof, "unexpected EOF").into());
+            return Err(DecodeError::UnexpectedEof);
         }
         let b = &self.buf.as_ref()[self.pos..self.pos + n];
         self.pos += n;
@@ -96,14 +204,238 @@ where
 
     /// read all remaining data
     #[inline]
-    pub fn read_all_bytes(&mut self) -> Result<&[u8]> {
+    pub fn read_all_bytes(&mut self) -> Result<&[u8], DecodeError> {
         self.read_bytes(self.remain)
     }
 
     /// read data to specified buffer, fills the buffer
     #[inline]
-    pub fn read_bytes_into(&mut self, dst: &mut [u8]) -> Result<()> {
+    pub fn read_bytes_into(&mut self, dst: &mut [u8]) -> Result<(), DecodeError> {
         dst.copy_from_slice(self.read_bytes(dst.len())?);
         Ok(())
     }
 }
+
+impl<'a> Reader<&'a [u8]> {
+    /// like [`Reader::read_bytes`], but the slice borrows the data instead of the reader
+    #[inline]
+    pub fn read_slice(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        if self.remain < n {
+            return Err(DecodeError::UnexpectedEof);
+        }
+        let b = &self.buf[self.pos..self.pos + n];
+        self.pos += n;
+        self.remain -= n;
+        Ok(b)
+    }
+}
+
+/// destination of encoded bytes
+///
+/// implemented for [`SliceWriter`], which writes into a caller-provided slice without
+/// allocating, and for every `std::io::Write` with the `std` feature, `Vec<u8>` without it
+pub trait BufMut {
+    /// write all bytes of `src`
+    fn put_slice(&mut self, src: &[u8]) -> Result<(), EncodeError>;
+
+    /// write 1 byte
+    #[inline]
+    fn put_u8(&mut self, b: u8) -> Result<(), EncodeError> {
+        self.put_slice(&[b])
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W> BufMut for W
+where
+    W: std::io::Write + ?Sized,
+{
+    #[inline]
+    fn put_slice(&mut self, src: &[u8]) -> Result<(), EncodeError> {
+        self.write_all(src)?;
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl BufMut for Vec<u8> {
+    #[inline]
+    fn put_slice(&mut self, src: &[u8]) -> Result<(), EncodeError> {
+        self.extend_from_slice(src);
+        Ok(())
+    }
+}
+
+/// writer over a fixed-size byte slice
+///
+/// example
+/// ```
+/// use protobuf_lite::buffer::SliceWriter;
+/// use protobuf_lite::varint::write_uvarint;
+/// fn main() {
+///     let mut data = [0u8; 4];
+///     let mut writer = SliceWriter::new(&mut data);
+///     write_uvarint(150, &mut writer).unwrap();
+///     assert_eq!(writer.position(), 2);
+///     assert_eq!(data, [0x96, 0x01, 0, 0]);
+/// }
+/// ```
+pub struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl<'a> SliceWriter<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        SliceWriter { buf, pos: 0 }
+    }
+
+    /// gets the number of bytes written
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// gets remaining space of the slice
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    /// gets the written part of the slice
+    #[inline]
+    pub fn written(&self) -> &[u8] {
+        &self.buf[..self.pos]
+    }
+}
+
+impl BufMut for SliceWriter<'_> {
+    #[inline]
+    fn put_slice(&mut self, src: &[u8]) -> Result<(), EncodeError> {
+        if self.remaining() < src.len() {
+            return Err(EncodeError::BufferTooSmall {
+                required: src.len(),
+                remaining: self.remaining(),
+            });
+        }
+        self.buf[self.pos..self.pos + src.len()].copy_from_slice(src);
+        self.pos += src.len();
+        Ok(())
+    }
+
+    #[inline]
+    fn put_u8(&mut self, b: u8) -> Result<(), EncodeError> {
+        if self.pos >= self.buf.len() {
+            return Err(EncodeError::BufferTooSmall {
+                required: 1,
+                remaining: 0,
+            });
+        }
+        self.buf[self.pos] = b;
+        self.pos += 1;
+        Ok(())
+    }
+}
+
+/// growable writer which encodes nested messages without building a [`crate::protobuf::Map`]
+///
+/// [`Writer::begin_message`] writes the tag and reserves one byte for the length,
+/// [`Writer::end_message`] writes the real length there and shifts the body if it needs more
+///
+/// example
+/// ```
+/// use protobuf_lite::buffer::Writer;
+/// use protobuf_lite::protobuf::{decode_protobuf, Map, ProtoData};
+/// fn main() {
+///     let mut writer = Writer::new();
+///     writer.write_field(1, &ProtoData::Varint(150)).unwrap();
+///     writer.begin_message(2).unwrap();
+///     writer.write_field(1, &"hello".into()).unwrap();
+///     writer.end_message().unwrap();
+///     let data = writer.into_inner().unwrap();
+///
+///     let mut inner = Map::new();
+///     inner.insert(1, "hello".into());
+///     let mut pb = Map::new();
+///     pb.insert(1, ProtoData::Varint(150));
+///     pb.insert(2, inner.into());
+///     assert_eq!(data, pb.encode().unwrap());
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct Writer {
+    buf: Vec<u8>,
+    // positions of the reserved length bytes of open messages, innermost last
+    open: Vec<usize>,
+}
+
+impl Writer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Writer {
+            buf: Vec::with_capacity(capacity),
+            open: Vec::new(),
+        }
+    }
+
+    /// write the tag of a message field and start its body
+    pub fn begin_message(&mut self, field: u64) -> Result<(), EncodeError> {
+        write_uvarint((field << 3) | (WireType::LEN as u64), &mut self.buf)?;
+        self.open.push(self.buf.len());
+        self.buf.push(0);
+        Ok(())
+    }
+
+    /// finish the innermost open message and write its length
+    pub fn end_message(&mut self) -> Result<(), EncodeError> {
+        let pos = self.open.pop().ok_or(EncodeError::NoOpenMessage)?;
+        let len = self.buf.len() - pos - 1;
+        if len < 0x80 {
+            self.buf[pos] = len as u8;
+        } else {
+            self.buf.splice(pos..pos + 1, encode_uvarint(len as u64));
+        }
+        Ok(())
+    }
+
+    /// write a field with its tag
+    pub fn write_field(&mut self, field: u64, data: &ProtoData) -> Result<(), EncodeError> {
+        data.encode_to(field, &mut self.buf)
+    }
+
+    /// gets the number of messages which are not finished yet
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.open.len()
+    }
+
+    /// gets the number of bytes written, reserved length bytes included
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// gets the encoded bytes, fails if a message is still open
+    pub fn into_inner(self) -> Result<Vec<u8>, EncodeError> {
+        if !self.open.is_empty() {
+            return Err(EncodeError::UnclosedMessage(self.open.len()));
+        }
+        Ok(self.buf)
+    }
+}
+
+impl BufMut for Writer {
+    #[inline]
+    fn put_slice(&mut self, src: &[u8]) -> Result<(), EncodeError> {
+        self.buf.extend_from_slice(src);
+        Ok(())
+    }
+}
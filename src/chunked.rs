@@ -0,0 +1,130 @@
+//! decoding over [`bytes::Buf`], for data which arrives as chained chunks instead of one slice
+//!
+//! length-delimited values stay [`Bytes`], build [`ProtoData`] from a value with `into` when an
+//! owned copy is needed
+use crate::error::DecodeError;
+use crate::protobuf::{ProtoData, WireType};
+use crate::varint::{decode_uvarint, MAX_VARINT_LENGTH};
+use alloc::vec::Vec;
+use bytes::{Buf, Bytes};
+
+/// raw value of a field, length-delimited values are not interpreted
+///
+/// for input backed by [`Bytes`] the `Bytes` values share its memory instead of copying
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RawValue {
+    Varint(u64),
+    Fix64(i64),
+    Fix32(i32),
+    Bytes(Bytes),
+}
+
+impl From<RawValue> for ProtoData {
+    fn from(v: RawValue) -> Self {
+        match v {
+            RawValue::Varint(v) => ProtoData::Varint(v),
+            RawValue::Fix64(v) => ProtoData::Fix64(v),
+            RawValue::Fix32(v) => ProtoData::Fix32(v),
+            RawValue::Bytes(v) => ProtoData::Bytes(v.to_vec()),
+        }
+    }
+}
+
+/// read a varint, it may be split across chunks
+pub fn read_uvarint<B>(buf: &mut B) -> Result<u64, DecodeError>
+where
+    B: Buf,
+{
+    let chunk = buf.chunk();
+    // the varint is complete in the current chunk if it has enough bytes or is the last one
+    if chunk.len() >= MAX_VARINT_LENGTH || chunk.len() == buf.remaining() {
+        let (x, n) = decode_uvarint(chunk)?;
+        buf.advance(n);
+        return Ok(x);
+    }
+    let mut x: u64 = 0;
+    let mut shift = 0;
+    for i in 0..MAX_VARINT_LENGTH {
+        if !buf.has_remaining() {
+            return Err(match i {
+                0 => DecodeError::EOF,
+                _ => DecodeError::UnexpectedEof,
+            });
+        }
+        let b = buf.get_u8();
+        x |= ((b & 0x7f) as u64) << shift;
+        shift += 7;
+        if b < 0x80 {
+            return Ok(x);
+        }
+    }
+    Err(DecodeError::OverFlow64Bit)
+}
+
+pub fn read_tag<B>(buf: &mut B) -> Result<(u64, WireType), DecodeError>
+where
+    B: Buf,
+{
+    let tag = read_uvarint(buf)?;
+    Ok((tag >> 3, WireType::try_from(tag & 0x07)?))
+}
+
+/// read the value of a field, the tag has already been consumed
+pub fn read_value<B>(wire_type: WireType, buf: &mut B) -> Result<RawValue, DecodeError>
+where
+    B: Buf,
+{
+    let value = match wire_type {
+        WireType::VARINT => RawValue::Varint(read_uvarint(buf)?),
+        WireType::I64 => {
+            if buf.remaining() < size_of::<i64>() {
+                return Err(DecodeError::UnexpectedEof);
+            }
+            RawValue::Fix64(buf.get_i64_le())
+        }
+        WireType::I32 => {
+            if buf.remaining() < size_of::<i32>() {
+                return Err(DecodeError::UnexpectedEof);
+            }
+            RawValue::Fix32(buf.get_i32_le())
+        }
+        WireType::LEN => {
+            let len = read_uvarint(buf)? as usize;
+            if buf.remaining() < len {
+                return Err(DecodeError::UnexpectedEof);
+            }
+            RawValue::Bytes(buf.copy_to_bytes(len))
+        }
+        x => return Err(DecodeError::DeprecatedWireType(x)),
+    };
+    Ok(value)
+}
+
+/// read all fields of one message level in wire order
+///
+/// nested messages are left as [`RawValue::Bytes`] and can be decoded again with this function,
+/// which does not copy them if the input is [`Bytes`]
+///
+/// example
+/// ```
+/// use bytes::{Buf, Bytes};
+/// use protobuf_lite::chunked::{decode_fields, RawValue};
+/// fn main() {
+///     // the varint 150 is split across both chunks
+///     let data = Bytes::from_static(&[0x08, 0x96]).chain(Bytes::from_static(&[0x01, 0x12, 0x01, 0x61]));
+///     let fields = decode_fields(data).unwrap();
+///     assert_eq!(fields[0], (1, RawValue::Varint(150)));
+///     assert_eq!(fields[1], (2, RawValue::Bytes(Bytes::from_static(b"a"))));
+/// }
+/// ```
+pub fn decode_fields<B>(mut buf: B) -> Result<Vec<(u64, RawValue)>, DecodeError>
+where
+    B: Buf,
+{
+    let mut fields = Vec::new();
+    while buf.has_remaining() {
+        let (field, wire_type) = read_tag(&mut buf)?;
+        fields.push((field, read_value(wire_type, &mut buf)?));
+    }
+    Ok(fields)
+}
@@ -0,0 +1,164 @@
+use crate::buffer::Reader;
+use crate::error::{DecodeError, EncodeError};
+use crate::protobuf::{decode_protobuf, Map, ProtoData};
+use crate::varint::{read_uvarint, uvarint_len, write_uvarint, MAX_VARINT_LENGTH};
+use bytes::{Buf, BufMut, BytesMut};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// default upper bound of a single delimited message, 64 MiB
+pub const DEFAULT_MAX_LENGTH: usize = 64 * 1024 * 1024;
+
+/// read one varint length-delimited message
+///
+/// returns `None` if the reader is closed before the first byte of the message,
+/// messages longer than [`DEFAULT_MAX_LENGTH`] are rejected
+pub async fn read_delimited<R>(reader: &mut R) -> Result<Option<ProtoData>, DecodeError>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut len: u64 = 0;
+    for i in 0..MAX_VARINT_LENGTH {
+        let b = match reader.read_u8().await {
+            Ok(b) => b as u64,
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+                if i == 0 {
+                    return Ok(None);
+                }
+                return Err(DecodeError::UnexpectedEof);
+            }
+            Err(err) => return Err(err.into()),
+        };
+        // only bit 63 is left for the 10th byte, higher bits would be shifted out
+        if i == MAX_VARINT_LENGTH - 1 && b > 1 {
+            return Err(DecodeError::OverFlow64Bit);
+        }
+        len |= (b & 0x7F) << (i * 7);
+        if b & 0x80 == 0 {
+            if len as usize > DEFAULT_MAX_LENGTH {
+                return Err(DecodeError::MessageTooLarge(len as usize));
+            }
+            let mut data = vec![0u8; len as usize];
+            reader
+                .read_exact(&mut data)
+                .await
+                .map_err(|_| DecodeError::UnexpectedEof)?;
+            return Ok(Some(decode_protobuf(data)?));
+        }
+    }
+    Err(DecodeError::OverFlow64Bit)
+}
+
+/// write a message prefixed with its varint encoded length
+pub async fn write_delimited<W>(
+    writer: &mut W,
+    msg: &Map<u64, ProtoData>,
+) -> Result<(), EncodeError>
+where
+    W: AsyncWrite + Unpin,
+{
+    let len = msg.encoded_len();
+    let mut buf = Vec::with_capacity(uvarint_len(len as u64) + len);
+    write_uvarint(len as u64, &mut buf)?;
+    msg.encode_to(&mut buf)?;
+    writer.write_all(buf.as_slice()).await?;
+    Ok(())
+}
+
+/// `tokio_util` codec for varint length-delimited protobuf messages
+///
+/// example
+/// ```
+/// use bytes::BytesMut;
+/// use protobuf_lite::codec::ProtoCodec;
+/// use protobuf_lite::protobuf::Map;
+/// use tokio_util::codec::{Decoder, Encoder};
+/// fn main() {
+///     let mut pb = Map::new();
+///     pb.insert(1, 150.into());
+///     let mut codec = ProtoCodec::new();
+///     let mut buf = BytesMut::new();
+///     codec.encode(&pb, &mut buf).unwrap();
+///     assert_eq!(codec.decode(&mut buf).unwrap(), Some(pb.into()));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProtoCodec {
+    max_length: usize,
+}
+
+impl ProtoCodec {
+    /// create a codec with [`DEFAULT_MAX_LENGTH`]
+    pub fn new() -> Self {
+        Self::with_max_length(DEFAULT_MAX_LENGTH)
+    }
+
+    /// create a codec which rejects messages longer than `max_length`
+    pub fn with_max_length(max_length: usize) -> Self {
+        ProtoCodec { max_length }
+    }
+
+    /// gets the maximum message length
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+}
+
+impl Default for ProtoCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder for ProtoCodec {
+    type Item = ProtoData;
+    type Error = DecodeError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, DecodeError> {
+        let mut reader = Reader::new(&src[..]);
+        let len = match read_uvarint(&mut reader) {
+            Ok(len) => len as usize,
+            Err(DecodeError::EOF | DecodeError::UnexpectedEof) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        if len > self.max_length {
+            return Err(DecodeError::MessageTooLarge(len));
+        }
+        let header = src.len() - reader.remaining();
+        if reader.remaining() < len {
+            src.reserve(len - reader.remaining());
+            return Ok(None);
+        }
+        src.advance(header);
+        let data = src.split_to(len);
+        Ok(Some(decode_protobuf(data)?))
+    }
+}
+
+impl Encoder<&Map<u64, ProtoData>> for ProtoCodec {
+    type Error = EncodeError;
+
+    fn encode(
+        &mut self,
+        item: &Map<u64, ProtoData>,
+        dst: &mut BytesMut,
+    ) -> Result<(), EncodeError> {
+        let len = item.encoded_len();
+        if len > self.max_length {
+            return Err(EncodeError::MessageTooLarge(len));
+        }
+        dst.reserve(uvarint_len(len as u64) + len);
+        let mut writer = dst.writer();
+        write_uvarint(len as u64, &mut writer)?;
+        item.encode_to(&mut writer)?;
+        Ok(())
+    }
+}
+
+impl Encoder<Map<u64, ProtoData>> for ProtoCodec {
+    type Error = EncodeError;
+
+    fn encode(&mut self, item: Map<u64, ProtoData>, dst: &mut BytesMut) -> Result<(), EncodeError> {
+        self.encode(&item, dst)
+    }
+}
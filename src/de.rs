@@ -0,0 +1,608 @@
+//! deserialize rust structs from protobuf wire format
+//!
+//! the field numbering and type mapping mirror [`crate::ser`]. fields missing from the input
+//! get the protobuf default value (`0`, `""`, empty collections, `None`), packed repeated
+//! scalars are accepted as well as unpacked ones
+use crate::buffer::Reader;
+use crate::error::SerdeError;
+use crate::fixint::{read_fix32, read_fix64};
+use crate::protobuf::{read_tag, WireType};
+use crate::ser::field_number;
+use crate::varint::read_uvarint;
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+use std::collections::BTreeMap;
+use std::str;
+
+type Result<T> = std::result::Result<T, SerdeError>;
+
+/// deserialize a struct from protobuf bytes
+///
+/// example
+/// ```
+/// use serde::Deserialize;
+/// #[derive(Deserialize, Debug, PartialEq)]
+/// struct Request {
+///     id: u64,
+///     #[serde(rename = "3")]
+///     name: String,
+///     #[serde(rename = "4")]
+///     tags: Vec<String>,
+/// }
+/// fn main() {
+///     let data = vec![0x08, 0x96, 0x01, 0x1a, 0x01, b'a'];
+///     let req: Request = protobuf_lite::de::from_bytes(&data).unwrap();
+///     assert_eq!(req, Request { id: 150, name: "a".into(), tags: vec![] });
+/// }
+/// ```
+pub fn from_bytes<'de, T>(data: &'de [u8]) -> Result<T>
+where
+    T: de::Deserialize<'de>,
+{
+    T::deserialize(Deserializer::new(data))
+}
+
+#[derive(Clone, Copy)]
+enum Raw<'de> {
+    Varint(u64),
+    Fixed32([u8; 4]),
+    Fixed64([u8; 8]),
+    Len(&'de [u8]),
+}
+
+#[derive(Clone, Copy)]
+enum Scalar {
+    Varint,
+    Fixed32,
+    Fixed64,
+}
+
+fn parse_fields(data: &[u8]) -> Result<BTreeMap<u64, Vec<Raw<'_>>>> {
+    let mut buf = Reader::new(data);
+    let mut fields: BTreeMap<u64, Vec<Raw>> = BTreeMap::new();
+    while !buf.is_end() {
+        let (field, wire_type) = read_tag(&mut buf)?;
+        let raw = match wire_type {
+            WireType::VARINT => Raw::Varint(read_uvarint(&mut buf)?),
+            WireType::I64 => {
+                let mut b = [0u8; 8];
+                buf.read_bytes_into(&mut b)?;
+                Raw::Fixed64(b)
+            }
+            WireType::I32 => {
+                let mut b = [0u8; 4];
+                buf.read_bytes_into(&mut b)?;
+                Raw::Fixed32(b)
+            }
+            WireType::LEN => {
+                let len = read_uvarint(&mut buf)? as usize;
+                let start = data.len() - buf.remaining();
+                buf.skip(len)?;
+                Raw::Len(&data[start..start + len])
+            }
+            x => return Err(SerdeError::UnexpectedWireType(x)),
+        };
+        fields.entry(field).or_default().push(raw);
+    }
+    Ok(fields)
+}
+
+fn read_packed<T>(buf: &mut Reader<T>, kind: Scalar) -> Result<u64>
+where
+    T: AsRef<[u8]>,
+{
+    Ok(match kind {
+        Scalar::Varint => read_uvarint(buf)?,
+        Scalar::Fixed32 => read_fix32(buf)? as u32 as u64,
+        Scalar::Fixed64 => read_fix64(buf)? as u64,
+    })
+}
+
+/// message level deserializer, only accepts structs
+pub struct Deserializer<'de> {
+    data: &'de [u8],
+}
+
+impl<'de> Deserializer<'de> {
+    /// create a deserializer over an encoded message
+    pub fn new(data: &'de [u8]) -> Self {
+        Deserializer { data }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+    type Error = SerdeError;
+
+    fn deserialize_any<V>(self, _: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(SerdeError::Unsupported("top-level value must be a struct"))
+    }
+
+    fn deserialize_unit_struct<V>(self, _: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V>(self, _: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_map(StructAccess {
+            names: fields,
+            index: 0,
+            used: Vec::new(),
+            fields: parse_fields(self.data)?,
+            pending: Vec::new(),
+        })
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes byte_buf option unit
+        seq tuple tuple_struct map enum identifier
+    }
+}
+
+/// yields every declared field of a struct, present in the input or not
+struct StructAccess<'de> {
+    names: &'static [&'static str],
+    index: usize,
+    used: Vec<u64>,
+    fields: BTreeMap<u64, Vec<Raw<'de>>>,
+    pending: Vec<Raw<'de>>,
+}
+
+impl<'de> de::MapAccess<'de> for StructAccess<'de> {
+    type Error = SerdeError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        let Some(&name) = self.names.get(self.index) else {
+            return Ok(None);
+        };
+        self.index += 1;
+        let field = field_number(name, self.index as u64, &mut self.used)?;
+        self.pending = self.fields.remove(&field).unwrap_or_default();
+        seed.deserialize(IntoDeserializer::<SerdeError>::into_deserializer(name))
+            .map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        seed.deserialize(ValueDeserializer::new(std::mem::take(&mut self.pending)))
+    }
+}
+
+/// deserializes a field from all of its occurrences in the message
+struct ValueDeserializer<'a, 'de> {
+    values: Vec<Raw<'de>>,
+    /// the sequence this is an element of, scalars are read from its packed values
+    seq: Option<&'a mut RepeatedAccess<'de>>,
+}
+
+impl<'de> ValueDeserializer<'_, 'de> {
+    fn new(values: Vec<Raw<'de>>) -> Self {
+        ValueDeserializer { values, seq: None }
+    }
+
+    fn scalar(self, kind: Scalar) -> Result<u64> {
+        let mut last = self.values.last().copied();
+        if let Some(seq) = self.seq {
+            if let Some(reader) = &mut seq.packed {
+                let v = read_packed(reader, kind)?;
+                if reader.is_end() {
+                    seq.packed = None;
+                }
+                return Ok(v);
+            }
+            // an empty packed value holds no elements, take the next occurrence instead
+            while let Some(Raw::Len([])) = last {
+                last = seq.values.next();
+                if last.is_none() {
+                    seq.ended = true;
+                    return Err(de::Error::custom("no element left"));
+                }
+            }
+            if let Some(Raw::Len(data)) = last {
+                let mut reader = Reader::new(data);
+                let v = read_packed(&mut reader, kind)?;
+                if !reader.is_end() {
+                    seq.packed = Some(reader);
+                }
+                return Ok(v);
+            }
+        }
+        match last {
+            None => Ok(0),
+            Some(Raw::Varint(v)) => Ok(v),
+            Some(Raw::Fixed32(b)) => Ok(u32::from_le_bytes(b) as u64),
+            Some(Raw::Fixed64(b)) => Ok(u64::from_le_bytes(b)),
+            Some(Raw::Len(data)) => {
+                let mut reader = Reader::new(data);
+                let mut v = 0;
+                while !reader.is_end() {
+                    v = read_packed(&mut reader, kind)?;
+                }
+                Ok(v)
+            }
+        }
+    }
+
+    fn len(&self) -> Result<&'de [u8]> {
+        match self.values.last() {
+            None => Ok(&[]),
+            Some(Raw::Len(data)) => Ok(data),
+            Some(Raw::Varint(_)) => Err(SerdeError::UnexpectedWireType(WireType::VARINT)),
+            Some(Raw::Fixed32(_)) => Err(SerdeError::UnexpectedWireType(WireType::I32)),
+            Some(Raw::Fixed64(_)) => Err(SerdeError::UnexpectedWireType(WireType::I64)),
+        }
+    }
+}
+
+macro_rules! deserialize_varint {
+    ($($method:ident => $visit:ident as $t:ty),*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                visitor.$visit(self.scalar(Scalar::Varint)? as $t)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'_, 'de> {
+    type Error = SerdeError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.values.last() {
+            None => visitor.visit_unit(),
+            Some(Raw::Varint(v)) => visitor.visit_u64(*v),
+            Some(Raw::Fixed32(b)) => visitor.visit_u32(u32::from_le_bytes(*b)),
+            Some(Raw::Fixed64(b)) => visitor.visit_u64(u64::from_le_bytes(*b)),
+            Some(Raw::Len(data)) => visitor.visit_borrowed_bytes(data),
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_bool(self.scalar(Scalar::Varint)? != 0)
+    }
+
+    deserialize_varint!(
+        deserialize_i8 => visit_i64 as i64,
+        deserialize_i16 => visit_i64 as i64,
+        deserialize_i32 => visit_i64 as i64,
+        deserialize_i64 => visit_i64 as i64,
+        deserialize_u8 => visit_u64 as u64,
+        deserialize_u16 => visit_u64 as u64,
+        deserialize_u32 => visit_u64 as u64,
+        deserialize_u64 => visit_u64 as u64
+    );
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_f32(f32::from_bits(self.scalar(Scalar::Fixed32)? as u32))
+    }
+
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_f64(f64::from_bits(self.scalar(Scalar::Fixed64)?))
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let v = self.scalar(Scalar::Varint)?;
+        match u32::try_from(v).ok().and_then(char::from_u32) {
+            Some(c) => visitor.visit_char(c),
+            None => Err(de::Error::invalid_value(
+                de::Unexpected::Unsigned(v),
+                &"a unicode scalar value",
+            )),
+        }
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let data = self.len()?;
+        match str::from_utf8(data) {
+            Ok(v) => visitor.visit_borrowed_str(v),
+            Err(_) => Err(de::Error::invalid_value(
+                de::Unexpected::Bytes(data),
+                &"a utf-8 string",
+            )),
+        }
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_borrowed_bytes(self.len()?)
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let packed = matches!(&self.seq, Some(seq) if seq.packed.is_some());
+        if self.values.is_empty() && !packed {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(self, _: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V>(self, _: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(RepeatedAccess {
+            values: self.values.into_iter(),
+            packed: None,
+            ended: false,
+        })
+    }
+
+    fn deserialize_tuple<V>(self, _: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(self, _: &'static str, _: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_map(MapEntryAccess {
+            entries: self.values.into_iter(),
+            value: Vec::new(),
+        })
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_struct(Deserializer::new(self.len()?), name, fields, visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let (index, values) = match self.values.last().copied() {
+            None => (0, Vec::new()),
+            Some(Raw::Len(data)) => match parse_fields(data)?.pop_first() {
+                Some((field, values)) if field > 0 => (field - 1, values),
+                _ => return Err(de::Error::custom("empty enum variant message")),
+            },
+            Some(_) => (self.scalar(Scalar::Varint)?, Vec::new()),
+        };
+        visitor.visit_enum(EnumAccess { index, values })
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+}
+
+/// yields every occurrence of a repeated field, expanding packed scalars
+struct RepeatedAccess<'de> {
+    values: std::vec::IntoIter<Raw<'de>>,
+    packed: Option<Reader<&'de [u8]>>,
+    /// set when a scalar element found only empty packed values left
+    ended: bool,
+}
+
+impl<'de> de::SeqAccess<'de> for RepeatedAccess<'de> {
+    type Error = SerdeError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let values = if self.packed.is_some() {
+            Vec::new()
+        } else {
+            match self.values.next() {
+                Some(raw) => vec![raw],
+                None => return Ok(None),
+            }
+        };
+        let result = seed.deserialize(ValueDeserializer {
+            values,
+            seq: Some(self),
+        });
+        match result {
+            Err(_) if self.ended => Ok(None),
+            result => result.map(Some),
+        }
+    }
+}
+
+/// yields the `{1: key, 2: value}` entries of a map field
+struct MapEntryAccess<'de> {
+    entries: std::vec::IntoIter<Raw<'de>>,
+    value: Vec<Raw<'de>>,
+}
+
+impl<'de> de::MapAccess<'de> for MapEntryAccess<'de> {
+    type Error = SerdeError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        let Some(entry) = self.entries.next() else {
+            return Ok(None);
+        };
+        let mut fields = match entry {
+            Raw::Len(data) => parse_fields(data)?,
+            _ => return Err(de::Error::custom("map entry must be a message")),
+        };
+        self.value = fields.remove(&2).unwrap_or_default();
+        let key = fields.remove(&1).unwrap_or_default();
+        seed.deserialize(ValueDeserializer::new(key)).map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        seed.deserialize(ValueDeserializer::new(std::mem::take(&mut self.value)))
+    }
+}
+
+/// a unit variant from a varint, or a variant message `{index + 1: value}`
+struct EnumAccess<'de> {
+    index: u64,
+    values: Vec<Raw<'de>>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess<'de> {
+    type Error = SerdeError;
+    type Variant = ValueDeserializer<'de, 'de>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(IntoDeserializer::<SerdeError>::into_deserializer(
+            self.index,
+        ))?;
+        Ok((variant, ValueDeserializer::new(self.values)))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for ValueDeserializer<'_, 'de> {
+    type Error = SerdeError;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V>(self, _: usize, _: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(SerdeError::Unsupported("tuple variant"))
+    }
+
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_struct(self, "", fields, visitor)
+    }
+}
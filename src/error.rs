@@ -1,4 +1,7 @@
 use crate::protobuf::WireType;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -9,6 +12,9 @@ pub enum DecodeError {
     #[error("overflow 32bit")]
     OverFlow32Bit,
 
+    #[error("overlong varint encoding")]
+    OverlongVarint,
+
     #[error("Unknown wire type: {0}")]
     UnknownWireType(u64),
 
@@ -21,12 +27,170 @@ pub enum DecodeError {
     #[error("EOF")]
     EOF,
 
+    #[error("compressed grpc frame without a decompressor")]
+    CompressedFrame,
+
+    #[error("invalid grpc compression flag: {0}")]
+    InvalidCompressionFlag(u8),
+
+    #[error("message too large: {0} bytes")]
+    MessageTooLarge(usize),
+
+    #[error("field {field}: expected {expected} wire type, got {actual}")]
+    UnexpectedWireType {
+        field: u64,
+        expected: WireType,
+        actual: WireType,
+    },
+
+    #[error("invalid value for field {0}")]
+    InvalidFieldValue(u64),
+
+    #[error("invalid hex: {0}")]
+    InvalidHex(hex::FromHexError),
+
+    #[error("invalid utf-8: {0}")]
+    InvalidUtf8(#[from] core::str::Utf8Error),
+
+    #[error("decompress grpc frame: {0}")]
+    Decompress(Box<dyn core::error::Error + Send + Sync>),
+
+    #[cfg(feature = "std")]
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
     #[error("unknown error")]
     Error,
 }
 
 #[derive(Debug, Error)]
 pub enum EncodeError {
-    #[error("data error")]
-    DataError,
+    #[error("field {path}: repeated element {index} is {actual}, expected {expected}")]
+    MixedRepeated {
+        path: FieldPath,
+        index: usize,
+        expected: &'static str,
+        actual: &'static str,
+    },
+
+    #[error("field {path}: repeated element {index} is a nested repeated value")]
+    NestedRepeated { path: FieldPath, index: usize },
+
+    #[error("grpc frame too large: {0} bytes")]
+    FrameTooLarge(usize),
+
+    #[error("message too large: {0} bytes")]
+    MessageTooLarge(usize),
+
+    #[error("buffer too small: {required} bytes required, {remaining} remaining")]
+    BufferTooSmall { required: usize, remaining: usize },
+
+    #[error("end_message called without an open message")]
+    NoOpenMessage,
+
+    #[error("{0} messages are still open")]
+    UnclosedMessage(usize),
+
+    #[cfg(feature = "std")]
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum PatchError {
+    #[error("field {0} not found")]
+    FieldNotFound(FieldPath),
+
+    #[error("field {0} is not a message")]
+    NotAMessage(FieldPath),
+
+    #[error("message field {0} occurs more than once")]
+    DuplicateField(FieldPath),
+
+    #[error("empty field path")]
+    EmptyPath,
+
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+}
+
+/// field numbers from the outermost message down to the field an error refers to, a field of
+/// repeated messages is followed by the element index, displayed dot separated like `2.0.1`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct FieldPath(pub Vec<u64>);
+
+impl Display for FieldPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        for (i, field) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ".")?;
+            }
+            write!(f, "{}", field)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Error)]
+pub enum SchemaError {
+    #[error("line {line}: {msg}")]
+    Parse { line: usize, msg: String },
+
+    #[error("unknown type: {0}")]
+    UnknownType(String),
+
+    #[error("import not found: {0}")]
+    ImportNotFound(String),
+
+    #[error("{0}: {1}")]
+    Io(String, #[source] std::io::Error),
+
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+}
+
+#[cfg(feature = "serde")]
+#[derive(Debug, Error)]
+pub enum SerdeError {
+    #[error("{0}")]
+    Message(String),
+
+    #[error("unsupported type: {0}")]
+    Unsupported(&'static str),
+
+    #[error("unexpected wire type: {0}")]
+    UnexpectedWireType(WireType),
+
+    #[error("field `{0}`: invalid field number {1}")]
+    InvalidFieldNumber(String, u64),
+
+    #[error("field `{0}`: field number {1} is already used")]
+    DuplicateFieldNumber(String, u64),
+
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+#[cfg(feature = "serde")]
+impl serde::ser::Error for SerdeError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        SerdeError::Message(msg.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::de::Error for SerdeError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        SerdeError::Message(msg.to_string())
+    }
 }
@@ -0,0 +1,315 @@
+//! pull parser over the wire format, yields one event per field without building a tree
+use crate::buffer::Reader;
+use crate::error::DecodeError;
+use crate::fixint::{read_fix32, read_fix64};
+use crate::protobuf::{read_tag, WireType};
+use crate::varint::read_uvarint;
+use core::iter::FusedIterator;
+
+/// raw value of a field, length-delimited values borrow the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Value<'a> {
+    Varint(u64),
+    Fix64(i64),
+    Fix32(i32),
+    Len(&'a [u8]),
+    /// start of a deprecated group, its fields follow as separate events
+    StartGroup,
+    /// end of a deprecated group
+    EndGroup,
+}
+
+impl<'a> Value<'a> {
+    /// gets the value of `uint64`, `uint32` and `enum` fields, or of `fixed64` and `fixed32`
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Value::Varint(v) => Some(v),
+            Value::Fix64(v) => Some(v as u64),
+            Value::Fix32(v) => Some(v as u32 as u64),
+            _ => None,
+        }
+    }
+
+    /// gets the value of `int64` and `int32` fields, or of `sfixed64` and `sfixed32`
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Value::Varint(v) => Some(v as i64),
+            Value::Fix64(v) => Some(v),
+            Value::Fix32(v) => Some(v as i64),
+            _ => None,
+        }
+    }
+
+    /// gets the value of zigzag encoded `sint64` and `sint32` fields
+    pub fn as_sint64(&self) -> Option<i64> {
+        match *self {
+            Value::Varint(v) => Some((v >> 1) as i64 ^ -((v & 1) as i64)),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Varint(v) => Some(v != 0),
+            _ => None,
+        }
+    }
+
+    pub fn as_f32(&self) -> Option<f32> {
+        match *self {
+            Value::Fix32(v) => Some(f32::from_bits(v as u32)),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Fix64(v) => Some(f64::from_bits(v as u64)),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match *self {
+            Value::Len(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// gets a length-delimited value if it is valid utf-8
+    pub fn as_str(&self) -> Option<&'a str> {
+        core::str::from_utf8(self.as_bytes()?).ok()
+    }
+}
+
+/// one field record of the wire format
+///
+/// all offsets are absolute positions of the reader the fields are read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Field<'a> {
+    pub number: u64,
+    pub wire_type: WireType,
+    /// position of the tag
+    pub offset: usize,
+    /// position of the value, behind the length prefix for length-delimited values
+    pub value_offset: usize,
+    /// position behind the value
+    pub end: usize,
+    pub value: Value<'a>,
+}
+
+/// iterator over the fields of one message level
+///
+/// nested messages are yielded as [`Value::Len`], the iterator stops after the first error
+///
+/// example
+/// ```
+/// use protobuf_lite::event::{Fields, Value};
+/// fn main() {
+///     let data = vec![0x08, 0x96, 0x01, 0x12, 0x02, 0x08, 0x01];
+///     let fields = Fields::new(&data).collect::<Result<Vec<_>, _>>().unwrap();
+///     assert_eq!(fields[0].value, Value::Varint(150));
+///     assert_eq!(fields[1].value, Value::Len(&[0x08, 0x01]));
+///     assert_eq!((fields[1].offset, fields[1].value_offset, fields[1].end), (3, 5, 7));
+/// }
+/// ```
+pub struct Fields<'a> {
+    buf: Reader<&'a [u8]>,
+    done: bool,
+}
+
+impl<'a> Fields<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self::from_reader(Reader::new(data))
+    }
+
+    /// iterate over the remaining bytes of a reader, offsets continue its positions
+    pub fn from_reader(buf: Reader<&'a [u8]>) -> Self {
+        Fields { buf, done: false }
+    }
+
+    /// gets the absolute position of the next field
+    pub fn position(&self) -> usize {
+        self.buf.position()
+    }
+
+    fn read_field(&mut self) -> Result<Field<'a>, DecodeError> {
+        let offset = self.buf.position();
+        let (number, wire_type) = read_tag(&mut self.buf)?;
+        let mut value_offset = self.buf.position();
+        #[allow(deprecated)]
+        let value = match wire_type {
+            WireType::VARINT => Value::Varint(read_uvarint(&mut self.buf)?),
+            WireType::I64 => Value::Fix64(read_fix64(&mut self.buf)?),
+            WireType::I32 => Value::Fix32(read_fix32(&mut self.buf)?),
+            WireType::LEN => {
+                let len = read_uvarint(&mut self.buf)?;
+                value_offset = self.buf.position();
+                Value::Len(self.buf.read_slice(len as usize)?)
+            }
+            WireType::SGROUP => Value::StartGroup,
+            WireType::EGROUP => Value::EndGroup,
+        };
+        Ok(Field {
+            number,
+            wire_type,
+            offset,
+            value_offset,
+            end: self.buf.position(),
+            value,
+        })
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = Result<Field<'a>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.buf.is_end() {
+            return None;
+        }
+        let field = self.read_field();
+        self.done = field.is_err();
+        Some(field)
+    }
+}
+
+impl FusedIterator for Fields<'_> {}
+
+/// callbacks for [`visit`], every method defaults to ignoring the value
+///
+/// example
+/// ```
+/// use protobuf_lite::error::DecodeError;
+/// use protobuf_lite::event::{visit, Visitor};
+/// use protobuf_lite::protobuf::{Map, ProtoData};
+///
+/// // sums all varints, descending into field 2 only
+/// struct Sum(u64);
+///
+/// impl Visitor for Sum {
+///     fn visit_varint(&mut self, _field: u64, v: u64) -> Result<(), DecodeError> {
+///         self.0 += v;
+///         Ok(())
+///     }
+///
+///     fn enter_message(&mut self, field: u64) -> Option<impl Visitor + '_> {
+///         (field == 2).then_some(self)
+///     }
+/// }
+///
+/// fn main() {
+///     let mut inner = Map::new();
+///     inner.insert(1, 2.into());
+///     let mut pb = Map::new();
+///     pb.insert(1, 1.into());
+///     pb.insert(2, inner.clone().into());
+///     pb.insert(3, inner.into());
+///     let mut sum = Sum(0);
+///     visit(&pb.encode().unwrap(), &mut sum).unwrap();
+///     assert_eq!(sum.0, 3);
+/// }
+/// ```
+pub trait Visitor {
+    fn visit_varint(&mut self, _field: u64, _v: u64) -> Result<(), DecodeError> {
+        Ok(())
+    }
+
+    fn visit_fixed64(&mut self, _field: u64, _v: i64) -> Result<(), DecodeError> {
+        Ok(())
+    }
+
+    fn visit_fixed32(&mut self, _field: u64, _v: i32) -> Result<(), DecodeError> {
+        Ok(())
+    }
+
+    /// called for length-delimited values which are not entered as a message
+    fn visit_bytes(&mut self, _field: u64, _v: &[u8]) -> Result<(), DecodeError> {
+        Ok(())
+    }
+
+    /// return a visitor to decode a length-delimited value as a nested message,
+    /// `None` passes it to [`Visitor::visit_bytes`] instead
+    fn enter_message(&mut self, _field: u64) -> Option<impl Visitor + '_> {
+        None::<IgnoredAny>
+    }
+
+    /// called after a message returned by [`Visitor::enter_message`] has been visited
+    fn leave_message(&mut self, _field: u64) -> Result<(), DecodeError> {
+        Ok(())
+    }
+}
+
+/// visitor which ignores everything
+pub struct IgnoredAny;
+
+impl Visitor for IgnoredAny {}
+
+impl<V> Visitor for &mut V
+where
+    V: Visitor + ?Sized,
+{
+    fn visit_varint(&mut self, field: u64, v: u64) -> Result<(), DecodeError> {
+        (**self).visit_varint(field, v)
+    }
+
+    fn visit_fixed64(&mut self, field: u64, v: i64) -> Result<(), DecodeError> {
+        (**self).visit_fixed64(field, v)
+    }
+
+    fn visit_fixed32(&mut self, field: u64, v: i32) -> Result<(), DecodeError> {
+        (**self).visit_fixed32(field, v)
+    }
+
+    fn visit_bytes(&mut self, field: u64, v: &[u8]) -> Result<(), DecodeError> {
+        (**self).visit_bytes(field, v)
+    }
+
+    fn enter_message(&mut self, field: u64) -> Option<impl Visitor + '_> {
+        (**self).enter_message(field)
+    }
+
+    fn leave_message(&mut self, field: u64) -> Result<(), DecodeError> {
+        (**self).leave_message(field)
+    }
+}
+
+/// decode a message and pass every field to the visitor, nothing is allocated
+pub fn visit<V>(data: &[u8], visitor: &mut V) -> Result<(), DecodeError>
+where
+    V: Visitor + ?Sized,
+{
+    visit_fields(Fields::new(data), visitor)
+}
+
+fn visit_fields<V>(fields: Fields<'_>, visitor: &mut V) -> Result<(), DecodeError>
+where
+    V: Visitor + ?Sized,
+{
+    for field in fields {
+        let field = field?;
+        match field.value {
+            Value::Varint(v) => visitor.visit_varint(field.number, v)?,
+            Value::Fix64(v) => visitor.visit_fixed64(field.number, v)?,
+            Value::Fix32(v) => visitor.visit_fixed32(field.number, v)?,
+            Value::Len(v) => {
+                let entered = match visitor.enter_message(field.number) {
+                    Some(mut child) => {
+                        let nested = Reader::with_offset(v, field.value_offset);
+                        visit_fields(Fields::from_reader(nested), &mut child)?;
+                        true
+                    }
+                    None => false,
+                };
+                match entered {
+                    true => visitor.leave_message(field.number)?,
+                    false => visitor.visit_bytes(field.number, v)?,
+                }
+            }
+            Value::StartGroup | Value::EndGroup => {
+                return Err(DecodeError::DeprecatedWireType(field.wire_type))
+            }
+        }
+    }
+    Ok(())
+}
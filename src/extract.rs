@@ -0,0 +1,127 @@
+//! read single fields by path, everything else is skipped using the length prefixes
+use crate::buffer::Reader;
+use crate::error::DecodeError;
+use crate::event::{Fields, Value};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// find the value of a field, `path` lists the field numbers from the outermost message
+///
+/// like a decoder the last occurrence wins: intermediate fields must be length-delimited and
+/// every occurrence of them is searched, as their messages would be merged, and the last match
+/// in wire order is returned. a message value is the last occurrence alone, it is not merged
+/// with earlier ones. the whole message is scanned
+///
+/// example
+/// ```
+/// use protobuf_lite::extract::extract_field;
+/// use protobuf_lite::protobuf::Map;
+/// fn main() {
+///     let mut header = Map::new();
+///     header.insert(1, 42.into());
+///     let mut pb = Map::new();
+///     pb.insert(1, header.into());
+///     pb.insert(2, "body".into());
+///     let data = pb.encode().unwrap();
+///
+///     let id = extract_field(&data, &[1, 1]).unwrap().unwrap();
+///     assert_eq!(id.as_u64(), Some(42));
+///     let body = extract_field(&data, &[2]).unwrap().unwrap();
+///     assert_eq!(body.as_str(), Some("body"));
+///     assert_eq!(extract_field(&data, &[3]).unwrap(), None);
+/// }
+/// ```
+pub fn extract_field<'a>(data: &'a [u8], path: &[u64]) -> Result<Option<Value<'a>>, DecodeError> {
+    find(Fields::new(data), path)
+}
+
+fn find<'a>(fields: Fields<'a>, path: &[u64]) -> Result<Option<Value<'a>>, DecodeError> {
+    let Some((&number, rest)) = path.split_first() else {
+        return Ok(None);
+    };
+    let mut result = None;
+    for field in fields {
+        let field = field?;
+        if field.number != number {
+            continue;
+        }
+        if rest.is_empty() {
+            result = Some(field.value);
+        } else if let Value::Len(v) = field.value {
+            let nested = Fields::from_reader(Reader::with_offset(v, field.value_offset));
+            if let Some(value) = find(nested, rest)? {
+                result = Some(value);
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// find the value of several fields in one pass like [`extract_field`], results are in the
+/// order of `paths`
+///
+/// example
+/// ```
+/// use protobuf_lite::extract::extract_fields;
+/// use protobuf_lite::protobuf::Map;
+/// fn main() {
+///     let mut header = Map::new();
+///     header.insert(1, 42.into());
+///     header.insert(2, "svc".into());
+///     let mut pb = Map::new();
+///     pb.insert(1, header.into());
+///     let data = pb.encode().unwrap();
+///
+///     let values = extract_fields(&data, &[&[1, 2], &[1, 1], &[5]]).unwrap();
+///     assert_eq!(values[0].and_then(|v| v.as_str()), Some("svc"));
+///     assert_eq!(values[1].and_then(|v| v.as_u64()), Some(42));
+///     assert_eq!(values[2], None);
+/// }
+/// ```
+pub fn extract_fields<'a>(
+    data: &'a [u8],
+    paths: &[&[u64]],
+) -> Result<Vec<Option<Value<'a>>>, DecodeError> {
+    let mut result = vec![None; paths.len()];
+    let active = (0..paths.len())
+        .filter(|&i| !paths[i].is_empty())
+        .collect::<Vec<_>>();
+    find_all(Fields::new(data), paths, 0, &active, &mut result)?;
+    Ok(result)
+}
+
+/// fill the results of the `active` paths whose remaining numbers start at `depth`
+fn find_all<'a>(
+    fields: Fields<'a>,
+    paths: &[&[u64]],
+    depth: usize,
+    active: &[usize],
+    result: &mut [Option<Value<'a>>],
+) -> Result<(), DecodeError> {
+    let mut nested = Vec::new();
+    for field in fields {
+        let field = field?;
+        nested.clear();
+        for &i in active {
+            if paths[i][depth] != field.number {
+                continue;
+            }
+            if paths[i].len() == depth + 1 {
+                result[i] = Some(field.value);
+            } else if matches!(field.value, Value::Len(_)) {
+                nested.push(i);
+            }
+        }
+        if let (Value::Len(v), false) = (field.value, nested.is_empty()) {
+            let reader = Reader::with_offset(v, field.value_offset);
+            find_all(
+                Fields::from_reader(reader),
+                paths,
+                depth + 1,
+                &nested,
+                result,
+            )?;
+        }
+    }
+    Ok(())
+}
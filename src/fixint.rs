@@ -1,55 +1,225 @@
-use crate::buffer::Reader;
-use anyhow::Result;
-use std::io::Write;
+use crate::buffer::{BufMut, Reader};
+use crate::error::{DecodeError, EncodeError};
+use alloc::vec::Vec;
+
+/// a fixed-size number which can be read and written in both byte orders
+///
+/// implemented for `i32`, `u32`, `f32`, `i64`, `u64` and `f64`, protobuf uses little-endian
+/// for `fixed32`, `sfixed32`, `float` and their 64-bit counterparts
+pub trait FixedInt: Copy {
+    /// byte array holding the encoded value
+    type Bytes: AsRef<[u8]> + AsMut<[u8]> + Default;
+
+    const SIZE: usize;
+
+    fn to_le(self) -> Self::Bytes;
+
+    fn from_le(bytes: Self::Bytes) -> Self;
+
+    fn to_be(self) -> Self::Bytes;
+
+    fn from_be(bytes: Self::Bytes) -> Self;
+}
+
+macro_rules! impl_fixed_int {
+    ($($t:ty),*) => {
+        $(
+            impl FixedInt for $t {
+                type Bytes = [u8; size_of::<$t>()];
+
+                const SIZE: usize = size_of::<$t>();
+
+                #[inline]
+                fn to_le(self) -> Self::Bytes {
+                    self.to_le_bytes()
+                }
+
+                #[inline]
+                fn from_le(bytes: Self::Bytes) -> Self {
+                    <$t>::from_le_bytes(bytes)
+                }
+
+                #[inline]
+                fn to_be(self) -> Self::Bytes {
+                    self.to_be_bytes()
+                }
+
+                #[inline]
+                fn from_be(bytes: Self::Bytes) -> Self {
+                    <$t>::from_be_bytes(bytes)
+                }
+            }
+        )*
+    };
+}
+
+impl_fixed_int!(i32, u32, f32, i64, u64, f64);
+
+/// write a fixed-size number in little-endian byte order
+#[inline]
+pub fn write_fixed<F, T>(x: F, buf: &mut T) -> Result<(), EncodeError>
+where
+    F: FixedInt,
+    T: BufMut + ?Sized,
+{
+    buf.put_slice(x.to_le().as_ref())
+}
+
+/// write a fixed-size number in big-endian byte order, as used by length prefixes of
+/// framing formats such as grpc
+#[inline]
+pub fn write_fixed_be<F, T>(x: F, buf: &mut T) -> Result<(), EncodeError>
+where
+    F: FixedInt,
+    T: BufMut + ?Sized,
+{
+    buf.put_slice(x.to_be().as_ref())
+}
+
+/// read a fixed-size number in little-endian byte order
+///
+/// example
+/// ```
+/// use protobuf_lite::buffer::Reader;
+/// use protobuf_lite::fixint::{encode_fixed, read_fixed};
+/// fn main() {
+///     let data = encode_fixed(1.5f32);
+///     assert_eq!(read_fixed::<f32, _>(&mut Reader::new(data.as_slice())).unwrap(), 1.5);
+/// }
+/// ```
+#[inline]
+pub fn read_fixed<F, T>(buf: &mut Reader<T>) -> Result<F, DecodeError>
+where
+    F: FixedInt,
+    T: AsRef<[u8]>,
+{
+    let mut b = F::Bytes::default();
+    buf.read_bytes_into(b.as_mut())?;
+    Ok(F::from_le(b))
+}
+
+/// read a fixed-size number in big-endian byte order
+#[inline]
+pub fn read_fixed_be<F, T>(buf: &mut Reader<T>) -> Result<F, DecodeError>
+where
+    F: FixedInt,
+    T: AsRef<[u8]>,
+{
+    let mut b = F::Bytes::default();
+    buf.read_bytes_into(b.as_mut())?;
+    Ok(F::from_be(b))
+}
+
+#[inline]
+pub fn encode_fixed<F>(x: F) -> Vec<u8>
+where
+    F: FixedInt,
+{
+    x.to_le().as_ref().to_vec()
+}
+
+#[inline]
+pub fn write_fix32<T>(x: i32, buf: &mut T) -> Result<(), EncodeError>
+where
+    T: BufMut + ?Sized,
+{
+    write_fixed(x, buf)
+}
+
+#[inline]
+pub fn write_fix64<T>(x: i64, buf: &mut T) -> Result<(), EncodeError>
+where
+    T: BufMut + ?Sized,
+{
+    write_fixed(x, buf)
+}
+
+#[inline]
+pub fn write_ufix32<T>(x: u32, buf: &mut T) -> Result<(), EncodeError>
+where
+    T: BufMut + ?Sized,
+{
+    write_fixed(x, buf)
+}
 
 #[inline]
-pub fn write_fix32<T>(x: i32, buf: &mut T) -> Result<()>
+pub fn write_ufix64<T>(x: u64, buf: &mut T) -> Result<(), EncodeError>
 where
-    T: Write,
+    T: BufMut + ?Sized,
 {
-    buf.write_all(x.to_le_bytes().as_slice())?;
-    Ok(())
+    write_fixed(x, buf)
 }
 
 #[inline]
-pub fn write_fix64<T>(x: i64, buf: &mut T) -> Result<()>
+pub fn write_float<T>(x: f32, buf: &mut T) -> Result<(), EncodeError>
 where
-    T: Write,
+    T: BufMut + ?Sized,
 {
-    buf.write_all(x.to_le_bytes().as_slice())?;
-    Ok(())
+    write_fixed(x, buf)
+}
+
+#[inline]
+pub fn write_double<T>(x: f64, buf: &mut T) -> Result<(), EncodeError>
+where
+    T: BufMut + ?Sized,
+{
+    write_fixed(x, buf)
 }
 
 #[inline]
 pub fn encode_fix32(x: i32) -> Vec<u8> {
-    let mut buf = Vec::with_capacity(size_of::<i32>());
-    buf.extend_from_slice(&x.to_le_bytes());
-    buf
+    encode_fixed(x)
 }
 
 #[inline]
 pub fn encode_fix64(x: i64) -> Vec<u8> {
-    let mut buf = Vec::with_capacity(size_of::<i64>());
-    buf.extend_from_slice(&x.to_le_bytes());
-    buf
+    encode_fixed(x)
+}
+
+#[inline]
+pub fn read_fix32<T>(buf: &mut Reader<T>) -> Result<i32, DecodeError>
+where
+    T: AsRef<[u8]>,
+{
+    read_fixed(buf)
+}
+
+#[inline]
+pub fn read_fix64<T>(buf: &mut Reader<T>) -> Result<i64, DecodeError>
+where
+    T: AsRef<[u8]>,
+{
+    read_fixed(buf)
+}
+
+#[inline]
+pub fn read_ufix32<T>(buf: &mut Reader<T>) -> Result<u32, DecodeError>
+where
+    T: AsRef<[u8]>,
+{
+    read_fixed(buf)
+}
+
+#[inline]
+pub fn read_ufix64<T>(buf: &mut Reader<T>) -> Result<u64, DecodeError>
+where
+    T: AsRef<[u8]>,
+{
+    read_fixed(buf)
 }
 
 #[inline]
-pub fn read_fix32<T>(buf: &mut Reader<T>) -> Result<i32>
+pub fn read_float<T>(buf: &mut Reader<T>) -> Result<f32, DecodeError>
 where
     T: AsRef<[u8]>,
 {
-    let mut b = [0u8; size_of::<i32>()];
-    buf.read_bytes_into(&mut b)?;
-    Ok(i32::from_le_bytes(b))
+    read_fixed(buf)
 }
 
 #[inline]
-pub fn read_fix64<T>(buf: &mut Reader<T>) -> Result<i64>
+pub fn read_double<T>(buf: &mut Reader<T>) -> Result<f64, DecodeError>
 where
     T: AsRef<[u8]>,
 {
-    let mut b = [0u8; size_of::<i64>()];
-    buf.read_bytes_into(&mut b)?;
-    Ok(i64::from_le_bytes(b))
+    read_fixed(buf)
 }
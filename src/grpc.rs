@@ -0,0 +1,119 @@
+use crate::buffer::{BufMut, Reader};
+use crate::error::{DecodeError, EncodeError};
+use crate::fixint::{read_fixed_be, write_fixed_be};
+use crate::protobuf::{decode_protobuf, Map, ProtoData};
+use alloc::vec::Vec;
+
+/// length of the grpc frame header, 1 byte compression flag + 4 bytes big-endian length
+pub const GRPC_HEADER_LENGTH: usize = 5;
+
+/// write a message as an uncompressed grpc length-prefixed frame
+pub fn encode_grpc_frame_to<T>(msg: &Map<u64, ProtoData>, buf: &mut T) -> Result<(), EncodeError>
+where
+    T: BufMut + ?Sized,
+{
+    let len = msg.encoded_len();
+    let len = u32::try_from(len).map_err(|_| EncodeError::FrameTooLarge(len))?;
+    buf.put_u8(0)?;
+    write_fixed_be(len, buf)?;
+    msg.encode_to(buf)
+}
+
+/// encode a message as an uncompressed grpc length-prefixed frame
+///
+/// example
+/// ```
+/// use protobuf_lite::grpc::{decode_grpc_frames, encode_grpc_frame};
+/// use protobuf_lite::protobuf::Map;
+/// fn main() {
+///     let mut pb = Map::new();
+///     pb.insert(1, 150.into());
+///     let frame = encode_grpc_frame(&pb).unwrap();
+///     assert_eq!(frame, vec![0, 0, 0, 0, 3, 0x08, 0x96, 0x01]);
+///     assert_eq!(decode_grpc_frames(&frame).unwrap(), vec![pb.into()]);
+/// }
+/// ```
+pub fn encode_grpc_frame(msg: &Map<u64, ProtoData>) -> Result<Vec<u8>, EncodeError> {
+    let mut buf = Vec::with_capacity(GRPC_HEADER_LENGTH + msg.encoded_len());
+    encode_grpc_frame_to(msg, &mut buf)?;
+    Ok(buf)
+}
+
+/// encode several messages as consecutive grpc frames
+pub fn encode_grpc_frames<'a, I>(msgs: I) -> Result<Vec<u8>, EncodeError>
+where
+    I: IntoIterator<Item = &'a Map<u64, ProtoData>>,
+{
+    let mut buf = Vec::new();
+    for msg in msgs {
+        encode_grpc_frame_to(msg, &mut buf)?;
+    }
+    Ok(buf)
+}
+
+/// read one grpc frame and return the compression flag with the raw payload
+///
+/// returns [`DecodeError::EOF`] if the reader is already at the end
+pub fn read_grpc_frame<T>(buf: &mut Reader<T>) -> Result<(bool, &[u8]), DecodeError>
+where
+    T: AsRef<[u8]>,
+{
+    if buf.is_end() {
+        return Err(DecodeError::EOF);
+    }
+    if buf.remaining() < GRPC_HEADER_LENGTH {
+        return Err(DecodeError::UnexpectedEof);
+    }
+    let compressed = match buf.read_byte()? {
+        0 => false,
+        1 => true,
+        flag => return Err(DecodeError::InvalidCompressionFlag(flag)),
+    };
+    let len = read_fixed_be::<u32, _>(buf)? as usize;
+    let payload = buf
+        .read_bytes(len)
+        .map_err(|_| DecodeError::UnexpectedEof)?;
+    Ok((compressed, payload))
+}
+
+/// decode all grpc frames in the buffer, compressed frames are rejected
+pub fn decode_grpc_frames<T>(data: T) -> Result<Vec<ProtoData>, DecodeError>
+where
+    T: AsRef<[u8]>,
+{
+    decode_grpc_frames_with(data, |_| Err(DecodeError::CompressedFrame))
+}
+
+/// decode all grpc frames in the buffer, compressed frames are passed through `decompress`
+/// before decoding, errors of the decompressor can be wrapped in [`DecodeError::Decompress`]
+///
+/// example
+/// ```
+/// use protobuf_lite::grpc::decode_grpc_frames_with;
+/// fn main() {
+///     // a single "compressed" frame, the identity decompressor just copies the payload
+///     let data = vec![1, 0, 0, 0, 2, 0x08, 0x01];
+///     let msgs = decode_grpc_frames_with(&data, |payload| Ok(payload.to_vec())).unwrap();
+///     assert_eq!(msgs.len(), 1);
+/// }
+/// ```
+pub fn decode_grpc_frames_with<T, F>(
+    data: T,
+    mut decompress: F,
+) -> Result<Vec<ProtoData>, DecodeError>
+where
+    T: AsRef<[u8]>,
+    F: FnMut(&[u8]) -> Result<Vec<u8>, DecodeError>,
+{
+    let mut buf = Reader::new(data.as_ref());
+    let mut result = Vec::new();
+    loop {
+        match read_grpc_frame(&mut buf) {
+            Ok((false, payload)) => result.push(decode_protobuf(payload)?),
+            Ok((true, payload)) => result.push(decode_protobuf(decompress(payload)?)?),
+            Err(DecodeError::EOF) => break,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(result)
+}
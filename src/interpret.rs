@@ -0,0 +1,290 @@
+//! schemaless decoding which keeps every viable interpretation of length-delimited values
+//!
+//! [`crate::protobuf::decode_protobuf`] takes the first interpretation that succeeds, message
+//! before string before bytes. here each length-delimited value carries all candidates with a
+//! confidence score between 0 and 1, best first, so tools can show alternatives and pick another
+use crate::buffer::Reader;
+use crate::error::DecodeError;
+use crate::event::{Fields, Value};
+use crate::protobuf::{Map, ProtoData};
+use crate::varint::decode_uvarint;
+use alloc::collections::btree_map::Entry;
+use alloc::collections::BTreeMap;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::str;
+
+/// one field with its absolute offset
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnotatedField<'a> {
+    pub number: u64,
+    pub offset: usize,
+    pub value: AnnotatedValue<'a>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnnotatedValue<'a> {
+    Varint(u64),
+    Fix64(i64),
+    Fix32(i32),
+    Len(LenValue<'a>),
+}
+
+/// a length-delimited value and the ways it can be read
+#[derive(Debug, Clone, PartialEq)]
+pub struct LenValue<'a> {
+    pub data: &'a [u8],
+    /// absolute offset of the first byte of `data`
+    pub offset: usize,
+    /// viable interpretations, best first, never empty since bytes always fit
+    pub candidates: Vec<Candidate<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate<'a> {
+    pub score: f32,
+    pub interpretation: Interpretation<'a>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Interpretation<'a> {
+    Message(Vec<AnnotatedField<'a>>),
+    String(&'a str),
+    /// packed repeated varints
+    Packed(Vec<u64>),
+    Bytes,
+}
+
+impl LenValue<'_> {
+    pub fn best(&self) -> &Candidate<'_> {
+        &self.candidates[0]
+    }
+
+    /// move a candidate to the front, so it is used by [`LenValue::to_proto_data`], returns
+    /// `false` and keeps the order if there is no candidate at `index`
+    pub fn select(&mut self, index: usize) -> bool {
+        if index >= self.candidates.len() {
+            return false;
+        }
+        let candidate = self.candidates.remove(index);
+        self.candidates.insert(0, candidate);
+        true
+    }
+
+    /// convert the best interpretation
+    pub fn to_proto_data(&self) -> ProtoData {
+        match &self.best().interpretation {
+            Interpretation::Message(fields) => ProtoData::Message(to_map(fields)),
+            Interpretation::String(v) => ProtoData::String(v.to_string()),
+            Interpretation::Packed(v) => match v.as_slice() {
+                [v] => ProtoData::Varint(*v),
+                v => ProtoData::Repeated(v.iter().map(|&v| ProtoData::Varint(v)).collect()),
+            },
+            Interpretation::Bytes => ProtoData::Bytes(self.data.to_vec()),
+        }
+    }
+}
+
+impl AnnotatedValue<'_> {
+    pub fn to_proto_data(&self) -> ProtoData {
+        match self {
+            AnnotatedValue::Varint(v) => ProtoData::Varint(*v),
+            AnnotatedValue::Fix64(v) => ProtoData::Fix64(*v),
+            AnnotatedValue::Fix32(v) => ProtoData::Fix32(*v),
+            AnnotatedValue::Len(v) => v.to_proto_data(),
+        }
+    }
+}
+
+/// convert fields using the best interpretation of every length-delimited value
+pub fn to_map(fields: &[AnnotatedField<'_>]) -> Map<u64, ProtoData> {
+    let mut map = Map::new();
+    for field in fields {
+        let data = field.value.to_proto_data();
+        match map.entry(field.number) {
+            Entry::Occupied(mut entry) => match entry.get_mut() {
+                ProtoData::Repeated(list) => list.push(data),
+                existing => *existing = ProtoData::Repeated(vec![existing.clone(), data]),
+            },
+            Entry::Vacant(entry) => {
+                entry.insert(data);
+            }
+        }
+    }
+    map
+}
+
+/// decode a message with all interpretations of its length-delimited values
+///
+/// example
+/// ```
+/// use protobuf_lite::interpret::{decode_annotated, AnnotatedValue, Interpretation};
+/// fn main() {
+///     // field 1 holds "hello", which is no valid message
+///     let data = b"\x0a\x05hello";
+///     let fields = decode_annotated(data).unwrap();
+///     let AnnotatedValue::Len(value) = &fields[0].value else { panic!() };
+///     assert_eq!(value.best().interpretation, Interpretation::String("hello"));
+///     assert_eq!(value.candidates.last().unwrap().interpretation, Interpretation::Bytes);
+/// }
+/// ```
+pub fn decode_annotated(data: &[u8]) -> Result<Vec<AnnotatedField<'_>>, DecodeError> {
+    decode_fields(Fields::new(data))
+}
+
+fn decode_fields(fields: Fields<'_>) -> Result<Vec<AnnotatedField<'_>>, DecodeError> {
+    let mut result = Vec::new();
+    for field in fields {
+        let field = field?;
+        let value = match field.value {
+            Value::Varint(v) => AnnotatedValue::Varint(v),
+            Value::Fix64(v) => AnnotatedValue::Fix64(v),
+            Value::Fix32(v) => AnnotatedValue::Fix32(v),
+            Value::Len(v) => AnnotatedValue::Len(interpret(v, field.value_offset)),
+            Value::StartGroup | Value::EndGroup => {
+                return Err(DecodeError::DeprecatedWireType(field.wire_type))
+            }
+        };
+        result.push(AnnotatedField {
+            number: field.number,
+            offset: field.offset,
+            value,
+        });
+    }
+    Ok(result)
+}
+
+/// collect and score every viable interpretation of a length-delimited value
+pub fn interpret(data: &[u8], offset: usize) -> LenValue<'_> {
+    let mut candidates = Vec::new();
+    if let Some(candidate) = message_candidate(data, offset) {
+        candidates.push(candidate);
+    }
+    if let Some(candidate) = string_candidate(data) {
+        candidates.push(candidate);
+    }
+    if let Some(candidate) = packed_candidate(data) {
+        candidates.push(candidate);
+    }
+    candidates.push(Candidate {
+        score: 0.1,
+        interpretation: Interpretation::Bytes,
+    });
+    // stable, so equal scores keep the order message, string, packed, bytes
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    LenValue {
+        data,
+        offset,
+        candidates,
+    }
+}
+
+/// the whole value has to be consumed by well-formed fields, scored by how plausible the field
+/// numbers are and whether fields come in ascending order like encoders write them
+fn message_candidate(data: &[u8], offset: usize) -> Option<Candidate<'_>> {
+    if data.is_empty() {
+        return Some(Candidate {
+            score: 0.3,
+            interpretation: Interpretation::Message(Vec::new()),
+        });
+    }
+    let fields = decode_fields(Fields::from_reader(Reader::with_offset(data, offset))).ok()?;
+    let plausible = fields
+        .iter()
+        .map(|field| match field.number {
+            0 => 0.0,
+            1..=32 => 1.0,
+            33..=1000 => 0.8,
+            1001..=65535 => 0.4,
+            _ => 0.1,
+        })
+        .sum::<f32>()
+        / fields.len() as f32;
+    let ordered = match fields.len() {
+        1 => 1.0,
+        n => {
+            let pairs = fields.windows(2).filter(|w| w[0].number <= w[1].number);
+            pairs.count() as f32 / (n - 1) as f32
+        }
+    };
+    // a lone varint or fixed field is what short random data most often parses as
+    let single = match fields.as_slice() {
+        [field] if !matches!(field.value, AnnotatedValue::Len(_)) => 0.7,
+        _ => 1.0,
+    };
+    Some(Candidate {
+        score: (0.7 * plausible + 0.3 * ordered) * single * wire_types(&fields),
+        interpretation: Interpretation::Message(fields),
+    })
+}
+
+/// how typical the wire types are, groups are deprecated and already rejected by
+/// [`decode_fields`]. fixed-width fields are rarer than varint and length-delimited ones, and
+/// encoders use one wire type per field number except for packed and unpacked scalars
+fn wire_types(fields: &[AnnotatedField<'_>]) -> f32 {
+    let bit = |value: &AnnotatedValue<'_>| match value {
+        AnnotatedValue::Varint(_) => 1u8,
+        AnnotatedValue::Fix64(_) => 2,
+        AnnotatedValue::Fix32(_) => 4,
+        AnnotatedValue::Len(_) => 8,
+    };
+    let mut seen = BTreeMap::new();
+    for field in fields {
+        *seen.entry(field.number).or_insert(0) |= bit(&field.value);
+    }
+    let mixed = fields
+        .iter()
+        .filter(|field| {
+            let bits = seen[&field.number];
+            bits.count_ones() > 1 && bits != 1 | 8
+        })
+        .count();
+    let fixed = fields
+        .iter()
+        .filter(|field| bit(&field.value) & (2 | 4) != 0)
+        .count();
+    let n = fields.len() as f32;
+    (1.0 - 0.15 * fixed as f32 / n) * (1.0 - 0.5 * mixed as f32 / n)
+}
+
+/// valid utf-8, scored by the share of printable characters
+fn string_candidate(data: &[u8]) -> Option<Candidate<'_>> {
+    let v = str::from_utf8(data).ok()?;
+    if v.is_empty() {
+        return Some(Candidate {
+            score: 0.2,
+            interpretation: Interpretation::String(v),
+        });
+    }
+    let total = v.chars().count();
+    let printable = v
+        .chars()
+        .filter(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r'))
+        .count();
+    let ratio = printable as f32 / total as f32;
+    Some(Candidate {
+        score: 0.95 * ratio * ratio,
+        interpretation: Interpretation::String(v),
+    })
+}
+
+/// the whole value has to be consumed by varints, which are rarely the best guess
+fn packed_candidate(data: &[u8]) -> Option<Candidate<'_>> {
+    if data.is_empty() {
+        return None;
+    }
+    let mut values = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        let (v, n) = decode_uvarint(rest).ok()?;
+        values.push(v);
+        rest = &rest[n..];
+    }
+    // multi-byte varints make accidental matches less likely
+    let density = values.len() as f32 / data.len() as f32;
+    Some(Candidate {
+        score: 0.5 - 0.3 * density,
+        interpretation: Interpretation::Packed(values),
+    })
+}
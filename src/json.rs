@@ -1,3 +1,6 @@
+use alloc::format;
+use alloc::string::String;
+
 pub(crate) fn escape_string(s: &str) -> String {
     let mut result = String::new();
     for c in s.chars() {
@@ -9,6 +12,7 @@ pub(crate) fn escape_string(s: &str) -> String {
             '\t' => result.push_str("\\t"),
             '\x08' => result.push_str("\\b"),
             '\x0C' => result.push_str("\\f"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
             _ => result.push(c),
         }
     }
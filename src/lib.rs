@@ -1,8 +1,29 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
 pub mod buffer;
+#[cfg(feature = "bytes")]
+pub mod chunked;
+#[cfg(feature = "tokio")]
+pub mod codec;
+#[cfg(feature = "serde")]
+pub mod de;
 pub mod error;
+pub mod event;
+pub mod extract;
 pub mod fixint;
+pub mod grpc;
+pub mod interpret;
 mod json;
+#[cfg(feature = "std")]
+pub mod message;
+pub mod patch;
 pub mod protobuf;
+#[cfg(feature = "std")]
+pub mod schema;
+#[cfg(feature = "serde")]
+pub mod ser;
 pub mod varint;
 
 #[cfg(test)]
@@ -120,9 +141,1107 @@ mod tests {
         assert_eq!(pb, expect_pb.into());
     }
 
-    fn read_protobuf(hex_str: &str) -> anyhow::Result<ProtoData> {
-        let bytes = hex::decode(hex_str.replace(" ", ""))?;
-        decode_protobuf_from(&mut Reader::new(&bytes.as_slice()))
+    fn read_protobuf(hex_str: &str) -> Result<ProtoData, error::DecodeError> {
+        protobuf::decode_protobuf_hex(hex_str)
+    }
+
+    #[test]
+    fn grpc_frames() {
+        let mut first = Map::new();
+        first.insert(1, 114514.into());
+        let mut second = Map::new();
+        second.insert(2, "hello".into());
+
+        let data = grpc::encode_grpc_frames([&first, &second]).unwrap();
+        assert_eq!(
+            grpc::decode_grpc_frames(&data).unwrap(),
+            vec![first.clone().into(), second.into()]
+        );
+
+        let mut compressed = grpc::encode_grpc_frame(&first).unwrap();
+        compressed[0] = 1;
+        let err = grpc::decode_grpc_frames(&compressed).unwrap_err();
+        assert!(matches!(err, error::DecodeError::CompressedFrame));
+        assert_eq!(
+            grpc::decode_grpc_frames_with(&compressed, |v| Ok(v.to_vec())).unwrap(),
+            vec![first.into()]
+        );
+
+        let truncated = &data[..data.len() - 1];
+        assert!(grpc::decode_grpc_frames(truncated).is_err());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn delimited_stream() {
+        let mut first = Map::new();
+        first.insert(1, 114514.into());
+        let mut second = Map::new();
+        second.insert(2, "hello".into());
+
+        let mut data = Vec::new();
+        codec::write_delimited(&mut data, &first).await.unwrap();
+        codec::write_delimited(&mut data, &second).await.unwrap();
+
+        let mut reader = data.as_slice();
+        assert_eq!(
+            codec::read_delimited(&mut reader).await.unwrap(),
+            Some(first.clone().into())
+        );
+        assert_eq!(
+            codec::read_delimited(&mut reader).await.unwrap(),
+            Some(second.into())
+        );
+        assert_eq!(codec::read_delimited(&mut reader).await.unwrap(), None);
+
+        // a 10th length byte above 1 would wrap around to a length of 0
+        let mut overflow = [0x80u8; 10];
+        overflow[9] = 0x02;
+        assert!(matches!(
+            codec::read_delimited(&mut &overflow[..]).await,
+            Err(error::DecodeError::OverFlow64Bit)
+        ));
+
+        use tokio_util::codec::Decoder;
+        let mut codec = codec::ProtoCodec::new();
+        let mut buf = bytes::BytesMut::from(&data[..3]);
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+        buf.extend_from_slice(&data[3..]);
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(first.into()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let mut inner = Map::new();
+        inner.insert(1, ProtoData::Fix32(-1));
+        let mut pb = Map::new();
+        pb.extend([
+            (1, 150.into()),
+            (2, ProtoData::Fix64(7)),
+            (3, vec![1u8, 2, 3].into()),
+            (4, "hello".into()),
+            (5, vec![ProtoData::Varint(1), ProtoData::Varint(2)].into()),
+            (6, inner.into()),
+        ]);
+
+        let json = serde_json::to_string(&pb).unwrap();
+        assert_eq!(
+            json,
+            r#"{"1":{"varint":150},"2":{"fix64":7},"3":{"bytes":[1,2,3]},"4":{"string":"hello"},"5":{"repeated":[{"varint":1},{"varint":2}]},"6":{"message":{"1":{"fix32":-1}}}}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Map<u64, ProtoData>>(&json).unwrap(),
+            pb
+        );
+        assert_eq!(
+            serde_json::from_str::<protobuf::WireType>(
+                &serde_json::to_string(&protobuf::WireType::I32).unwrap()
+            )
+            .unwrap(),
+            protobuf::WireType::I32
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_wire_format() {
+        use ::serde::{Deserialize, Serialize};
+        use std::collections::BTreeMap;
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        enum Kind {
+            Empty,
+            Code(i32),
+            Detail { reason: String },
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Inner {
+            value: i64,
+            ratio: f32,
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Outer {
+            id: u64,
+            name: String,
+            #[serde(rename = "20")]
+            tags: Vec<String>,
+            numbers: Vec<u32>,
+            inner: Inner,
+            missing: Option<Inner>,
+            #[serde(with = "serde_bytes")]
+            raw: Vec<u8>,
+            kinds: Vec<Kind>,
+            attrs: BTreeMap<String, u64>,
+            score: f64,
+        }
+
+        let value = Outer {
+            id: 114514,
+            name: "hello".into(),
+            tags: vec!["a".into(), "b".into()],
+            numbers: vec![1, 300, 7],
+            inner: Inner {
+                value: -42,
+                ratio: 0.5,
+            },
+            missing: None,
+            raw: vec![0xde, 0xad],
+            kinds: vec![
+                Kind::Empty,
+                Kind::Code(-1),
+                Kind::Detail { reason: "x".into() },
+            ],
+            attrs: BTreeMap::from([("k".to_string(), 1), ("v".to_string(), 2)]),
+            score: 1.25,
+        };
+
+        let data = ser::to_bytes(&value).unwrap();
+        assert_eq!(de::from_bytes::<Outer>(&data).unwrap(), value);
+
+        let pb = protobuf::decode_protobuf(&data).unwrap();
+        let ProtoData::Message(pb) = pb else {
+            panic!("not a message")
+        };
+        assert_eq!(pb.get(&1), Some(&ProtoData::Varint(114514)));
+        assert_eq!(pb.get(&2), Some(&ProtoData::String("hello".into())));
+        assert!(matches!(pb.get(&20), Some(ProtoData::Repeated(v)) if v.len() == 2));
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Packed {
+            numbers: Vec<u64>,
+            fixed: Vec<f32>,
+        }
+        // field 1 packed varint [1, 150], field 2 packed float [1.0]
+        let data = hex::decode("0a03019601120400 00803f".replace(" ", "")).unwrap();
+        assert_eq!(
+            de::from_bytes::<Packed>(&data).unwrap(),
+            Packed {
+                numbers: vec![1, 150],
+                fixed: vec![1.0],
+            }
+        );
+        // empty packed values hold zero elements, wherever they occur
+        let data = hex::decode("0a00 0a0101 0a00 1200".replace(" ", "")).unwrap();
+        assert_eq!(
+            de::from_bytes::<Packed>(&data).unwrap(),
+            Packed {
+                numbers: vec![1],
+                fixed: vec![],
+            }
+        );
+
+        #[derive(Serialize)]
+        struct Duplicate {
+            first: u64,
+            #[serde(rename = "1")]
+            second: u64,
+        }
+        assert!(matches!(
+            ser::to_bytes(&Duplicate {
+                first: 1,
+                second: 2
+            }),
+            Err(error::SerdeError::DuplicateFieldNumber(_, 1))
+        ));
+        #[derive(Serialize)]
+        struct Zero {
+            #[serde(rename = "0")]
+            zero: u64,
+        }
+        assert!(matches!(
+            ser::to_bytes(&Zero { zero: 1 }),
+            Err(error::SerdeError::InvalidFieldNumber(_, 0))
+        ));
+    }
+
+    #[test]
+    fn encoded_len() {
+        for num in [0, 1, 127, 128, 16383, 16384, u32::MAX as u64, u64::MAX] {
+            assert_eq!(varint::uvarint_len(num), varint::encode_uvarint(num).len());
+        }
+        for num in [0, -1, 63, -64, 64, i64::MIN, i64::MAX] {
+            assert_eq!(varint::varint_len(num), varint::encode_varint(num).len());
+        }
+
+        let mut inner = Map::new();
+        inner.insert(1, "inner".into());
+        inner.insert(2, vec![ProtoData::Varint(1), ProtoData::Varint(300)].into());
+        let mut pb = Map::new();
+        pb.insert(1, 150.into());
+        pb.insert(2, ProtoData::Fix32(-1));
+        pb.insert(3, inner.clone().into());
+        pb.insert(
+            4,
+            vec![
+                ProtoData::Message(inner.clone()),
+                ProtoData::Message(inner.clone()),
+            ]
+            .into(),
+        );
+        pb.insert(5, vec![ProtoData::Fix64(1), ProtoData::Fix64(2)].into());
+        pb.insert(2048, vec![ProtoData::Bytes(vec![0xff; 200])].into());
+
+        let data = pb.encode().unwrap();
+        assert_eq!(pb.encoded_len(), data.len());
+        assert_eq!(data.capacity(), data.len());
+        assert_eq!(
+            hex::encode(inner.encode().unwrap()),
+            "0a05696e6e6572120301ac02"
+        );
+
+        // nested messages are written with their length prefix, packed values come back as bytes
+        inner.insert(2, ProtoData::Bytes(vec![0x01, 0xac, 0x02]));
+        let decoded = decode_protobuf_from(&mut Reader::new(data.as_slice())).unwrap();
+        let ProtoData::Message(decoded) = decoded else {
+            panic!("not a message")
+        };
+        assert_eq!(decoded.get(&3), Some(&ProtoData::Message(inner.clone())));
+        assert_eq!(
+            decoded.get(&4),
+            Some(&ProtoData::Repeated(vec![
+                ProtoData::Message(inner.clone()),
+                ProtoData::Message(inner),
+            ]))
+        );
+    }
+
+    #[test]
+    fn encode_into() {
+        let mut inner = Map::new();
+        inner.insert(1, "inner".into());
+        let mut pb = Map::new();
+        pb.insert(1, 150.into());
+        pb.insert(2, inner.into());
+        pb.insert(3, vec![ProtoData::Fix32(1), ProtoData::Fix32(2)].into());
+        let expected = pb.encode().unwrap();
+
+        // the same buffer is reused for every message
+        let mut buf = [0u8; 64];
+        for _ in 0..2 {
+            let n = pb.encode_into(&mut buf).unwrap();
+            assert_eq!(&buf[..n], expected.as_slice());
+        }
+
+        let too_small = &mut buf[..expected.len() - 1];
+        too_small.fill(0);
+        let err = pb.encode_into(too_small).unwrap_err();
+        assert!(matches!(err, error::EncodeError::BufferTooSmall { .. }));
+        assert!(too_small.iter().all(|&b| b == 0));
+
+        let mut writer = buffer::SliceWriter::new(&mut buf[..1]);
+        assert!(varint::write_uvarint(300, &mut writer).is_err());
+        assert_eq!(writer.position(), 0);
+    }
+
+    #[test]
+    fn typed_errors() {
+        let data = [0x80u8];
+        assert!(matches!(
+            varint::read_uvarint(&mut Reader::new(data.as_slice())),
+            Err(error::DecodeError::UnexpectedEof)
+        ));
+        assert!(matches!(
+            protobuf::decode_protobuf_hex("zz"),
+            Err(error::DecodeError::InvalidHex(_))
+        ));
+
+        let mut pb = Map::new();
+        pb.insert(7, vec![ProtoData::Varint(1), "a".into()].into());
+        assert!(matches!(
+            pb.encode(),
+            Err(error::EncodeError::MixedRepeated { index: 1, .. })
+        ));
+        pb.insert(7, vec![ProtoData::Repeated(vec![])].into());
+        assert!(matches!(
+            pb.encode(),
+            Err(error::EncodeError::NestedRepeated { index: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn validate() {
+        let mut inner = Map::new();
+        inner.insert(2, vec![ProtoData::Fix32(1), ProtoData::Fix64(2)].into());
+        let mut pb = Map::new();
+        pb.insert(
+            1,
+            vec![ProtoData::Message(inner.clone()), ProtoData::Message(inner)].into(),
+        );
+        pb.insert(
+            3,
+            vec!["a".into(), ProtoData::Repeated(vec![]), 1.into()].into(),
+        );
+        pb.insert(4, vec![ProtoData::Varint(1), ProtoData::Varint(2)].into());
+
+        let errors = pb.validate().unwrap_err();
+        let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "field 1.0.2: repeated element 1 is fix64, expected fix32",
+                "field 1.1.2: repeated element 1 is fix64, expected fix32",
+                "field 3: repeated element 1 is a nested repeated value",
+                "field 3: repeated element 2 is varint, expected string",
+            ]
+        );
+        assert!(matches!(
+            pb.encode(),
+            Err(error::EncodeError::MixedRepeated { path, .. }) if path.0 == vec![1, 0, 2]
+        ));
+        let mut buf = [0u8; 64];
+        assert!(pb.encode_into(&mut buf).is_err());
+        assert_eq!(buf, [0u8; 64]);
+
+        pb.remove(&1);
+        pb.remove(&3);
+        assert!(pb.validate().is_ok());
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn chunked() {
+        use ::bytes::{Buf, Bytes};
+        use chunked::RawValue;
+        use std::collections::VecDeque;
+
+        let mut inner = Map::new();
+        inner.insert(1, "hello".into());
+        let mut pb = Map::new();
+        pb.insert(1, ProtoData::Varint(u64::MAX));
+        pb.insert(2, ProtoData::Fix64(-2));
+        pb.insert(3, ProtoData::Message(inner.clone()));
+        pb.insert(4, ProtoData::Fix32(3));
+        let data = pb.encode().unwrap();
+
+        // split at every position, so each varint and fixed value crosses a chunk boundary once
+        for i in 0..=data.len() {
+            let (a, b) = data.split_at(i);
+            let buf = Bytes::copy_from_slice(a).chain(Bytes::copy_from_slice(b));
+            let fields = chunked::decode_fields(buf).unwrap();
+            assert_eq!(fields.len(), 4);
+            assert_eq!(fields[0], (1, RawValue::Varint(u64::MAX)));
+            assert_eq!(fields[1], (2, RawValue::Fix64(-2)));
+            assert_eq!(fields[3], (4, RawValue::Fix32(3)));
+
+            let mut deque = VecDeque::from(b.to_vec());
+            for &x in a.iter().rev() {
+                deque.push_front(x);
+            }
+            assert_eq!(chunked::decode_fields(deque).unwrap(), fields);
+        }
+
+        let data = Bytes::from(data);
+        let fields = chunked::decode_fields(data.clone()).unwrap();
+        let RawValue::Bytes(nested) = &fields[2].1 else {
+            panic!("expected bytes");
+        };
+        assert!(data.as_ptr_range().contains(&nested.as_ptr()));
+        assert_eq!(
+            chunked::decode_fields(nested.clone()).unwrap(),
+            vec![(1, RawValue::Bytes(Bytes::from_static(b"hello")))]
+        );
+
+        let truncated = data.slice(..data.len() - 1);
+        assert!(matches!(
+            chunked::decode_fields(truncated),
+            Err(error::DecodeError::UnexpectedEof)
+        ));
+    }
+
+    #[test]
+    fn events() {
+        use event::{Fields, Value};
+
+        let mut inner = Map::new();
+        inner.insert(1, ProtoData::Fix32(-1));
+        let mut pb = Map::new();
+        pb.insert(1, 150.into());
+        pb.insert(2, inner.into());
+        pb.insert(3, ProtoData::Fix64(1));
+        let mut data = pb.encode().unwrap();
+
+        let fields: Vec<_> = Fields::new(&data).map(Result::unwrap).collect();
+        assert_eq!(fields.len(), 3);
+        assert_eq!(
+            (fields[1].offset, fields[1].value_offset, fields[1].end),
+            (3, 5, 10)
+        );
+        assert_eq!(fields[2].value, Value::Fix64(1));
+        let Value::Len(nested) = fields[1].value else {
+            panic!("expected a length-delimited value");
+        };
+        let nested = Fields::from_reader(Reader::with_offset(nested, fields[1].value_offset))
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!((nested.offset, nested.value), (5, Value::Fix32(-1)));
+
+        // group markers are reported, decode_protobuf rejects them
+        let group = [0x0b, 0x08, 0x01, 0x0c];
+        let values: Vec<_> = Fields::new(&group).map(|f| f.unwrap().value).collect();
+        assert_eq!(
+            values,
+            vec![Value::StartGroup, Value::Varint(1), Value::EndGroup]
+        );
+        assert!(protobuf::decode_protobuf(group).is_err());
+
+        data.truncate(data.len() - 1);
+        let mut fields = Fields::new(&data);
+        assert!(fields.nth(2).unwrap().is_err());
+        assert!(fields.next().is_none());
+    }
+
+    #[test]
+    fn visitor() {
+        use event::Visitor;
+
+        #[derive(Default)]
+        struct Item {
+            id: u64,
+            name: String,
+        }
+
+        #[derive(Default)]
+        struct Order {
+            items: Vec<Item>,
+            price: f64,
+            skipped: usize,
+        }
+
+        impl Visitor for Item {
+            fn visit_varint(&mut self, field: u64, v: u64) -> Result<(), error::DecodeError> {
+                if field == 1 {
+                    self.id = v;
+                }
+                Ok(())
+            }
+
+            fn visit_bytes(&mut self, field: u64, v: &[u8]) -> Result<(), error::DecodeError> {
+                if field == 2 {
+                    self.name = std::str::from_utf8(v)?.to_string();
+                }
+                Ok(())
+            }
+        }
+
+        impl Visitor for Order {
+            fn visit_fixed64(&mut self, field: u64, v: i64) -> Result<(), error::DecodeError> {
+                if field == 2 {
+                    self.price = f64::from_bits(v as u64);
+                }
+                Ok(())
+            }
+
+            fn visit_bytes(&mut self, _field: u64, _v: &[u8]) -> Result<(), error::DecodeError> {
+                self.skipped += 1;
+                Ok(())
+            }
+
+            fn enter_message(&mut self, field: u64) -> Option<impl Visitor + '_> {
+                if field != 1 {
+                    return None;
+                }
+                self.items.push(Item::default());
+                self.items.last_mut()
+            }
+        }
+
+        let mut item = Map::new();
+        item.insert(1, 7.into());
+        item.insert(2, "apple".into());
+        let mut pb = Map::new();
+        pb.insert(
+            1,
+            vec![ProtoData::Message(item.clone()), item.into()].into(),
+        );
+        pb.insert(2, 1.5f64.into());
+        pb.insert(3, ProtoData::Bytes(vec![0xff]));
+        let data = pb.encode().unwrap();
+
+        let mut order = Order::default();
+        event::visit(&data, &mut order).unwrap();
+        assert_eq!(order.items.len(), 2);
+        assert_eq!(
+            (order.items[1].id, order.items[1].name.as_str()),
+            (7, "apple")
+        );
+        assert_eq!(order.price, 1.5);
+        assert_eq!(order.skipped, 1);
+
+        event::visit(&data, &mut event::IgnoredAny).unwrap();
+        assert!(event::visit(&data[..data.len() - 1], &mut event::IgnoredAny).is_err());
+    }
+
+    #[test]
+    fn extract() {
+        let mut header = Map::new();
+        header.insert(1, ProtoData::Varint(-3i64 as u64));
+        header.insert(2, ProtoData::Varint(5)); // zigzag -3
+        let mut other = Map::new();
+        other.insert(3, 2.5f32.into());
+        let mut pb = Map::new();
+        pb.insert(1, vec![ProtoData::Message(other), header.into()].into());
+        pb.insert(2, true.into());
+        pb.insert(4, vec![ProtoData::Varint(1), ProtoData::Varint(2)].into());
+        let data = pb.encode().unwrap();
+
+        let field = |path: &[u64]| extract::extract_field(&data, path).unwrap();
+        assert_eq!(field(&[1, 1]).unwrap().as_i64(), Some(-3));
+        assert_eq!(field(&[1, 2]).unwrap().as_sint64(), Some(-3));
+        assert_eq!(field(&[1, 3]).unwrap().as_f32(), Some(2.5));
+        assert_eq!(field(&[2]).unwrap().as_bool(), Some(true));
+        assert_eq!(field(&[4]).unwrap().as_bytes(), Some(&[1, 2][..]));
+        assert_eq!(field(&[2, 1]), None);
+        assert_eq!(field(&[]), None);
+
+        let paths: [&[u64]; 5] = [&[2], &[1, 2], &[1, 3], &[3], &[]];
+        let values = extract::extract_fields(&data, &paths).unwrap();
+        let expected: Vec<_> = paths
+            .iter()
+            .map(|p| extract::extract_field(&data, p).unwrap())
+            .collect();
+        assert_eq!(values, expected);
+
+        assert!(extract::extract_field(&data[..data.len() - 1], &[5]).is_err());
+
+        // the last occurrence wins, also when it is in a later occurrence of the parent
+        let data = [
+            0x08, 0x01, 0x08, 0x02, // 1: 1, 1: 2
+            0x12, 0x02, 0x08, 0x03, // 2: {1: 3}
+            0x12, 0x02, 0x10, 0x04, // 2: {2: 4}
+            0x12, 0x02, 0x08, 0x05, // 2: {1: 5}
+        ];
+        let field = |path: &[u64]| extract::extract_field(&data, path).unwrap();
+        assert_eq!(field(&[1]).unwrap().as_u64(), Some(2));
+        assert_eq!(field(&[2, 1]).unwrap().as_u64(), Some(5));
+        assert_eq!(field(&[2, 2]).unwrap().as_u64(), Some(4));
+        let values = extract::extract_fields(&data, &[&[1], &[2, 1], &[2, 2]]).unwrap();
+        let values: Vec<_> = values.iter().map(|v| v.and_then(|v| v.as_u64())).collect();
+        assert_eq!(values, vec![Some(2), Some(5), Some(4)]);
+    }
+
+    #[test]
+    fn patch() {
+        let mut inner = Map::new();
+        inner.insert(1, ProtoData::Bytes(vec![1; 120]));
+        inner.insert(2, 7.into());
+        let mut middle = Map::new();
+        middle.insert(3, inner.clone().into());
+        let mut data = vec![0x08, 0x81, 0x80, 0x00]; // overlong varint, kept as is
+        middle.encode_to(&mut data).unwrap();
+        data.extend_from_slice(&[0x08, 0x80, 0x00]);
+        let original = data.clone();
+
+        // growing the body moves the enclosing length prefix from 1 to 2 bytes
+        patch::patch_field(&mut data, &[3, 2], &ProtoData::Bytes(vec![2; 10])).unwrap();
+        let mut expected_inner = inner.clone();
+        expected_inner.insert(2, ProtoData::Bytes(vec![2; 10]));
+        let mut expected = vec![0x08, 0x81, 0x80, 0x00];
+        let mut expected_middle = Map::new();
+        expected_middle.insert(3, expected_inner.into());
+        expected_middle.encode_to(&mut expected).unwrap();
+        expected.extend_from_slice(&[0x08, 0x80, 0x00]);
+        assert_eq!(data, expected);
+
+        patch::patch_field(&mut data, &[3, 2], &7.into()).unwrap();
+        assert_eq!(data, original);
+
+        patch::insert_field(&mut data, &[3, 5], &"x".into()).unwrap();
+        assert_eq!(
+            extract::extract_field(&data, &[3, 5])
+                .unwrap()
+                .unwrap()
+                .as_str(),
+            Some("x")
+        );
+        assert_eq!(patch::delete_field(&mut data, &[3, 5]).unwrap(), 1);
+        assert_eq!(data, original);
+
+        // decoders read the last of both overlong field 1 values, so both are replaced
+        patch::patch_field(&mut data, &[1], &5.into()).unwrap();
+        assert_eq!(data[..data.len() - 2], original[4..original.len() - 3]);
+        assert_eq!(data[data.len() - 2..], [0x08, 0x05]);
+        data = original.clone();
+
+        assert_eq!(patch::delete_field(&mut data, &[1]).unwrap(), 2);
+        assert_eq!(data, &original[4..original.len() - 3]);
+        assert_eq!(patch::delete_field(&mut data, &[1]).unwrap(), 0);
+
+        assert!(matches!(
+            patch::patch_field(&mut data, &[3, 9], &1.into()),
+            Err(error::PatchError::FieldNotFound(path)) if path.0 == vec![3, 9]
+        ));
+        assert!(matches!(
+            patch::insert_field(&mut data, &[3, 2, 1], &1.into()),
+            Err(error::PatchError::NotAMessage(_))
+        ));
+        assert!(matches!(
+            patch::delete_field(&mut data, &[]),
+            Err(error::PatchError::EmptyPath)
+        ));
+        let mut merged = vec![0x0a, 0x02, 0x08, 0x01, 0x0a, 0x02, 0x08, 0x02];
+        assert!(matches!(
+            patch::patch_field(&mut merged, &[1, 1], &3.into()),
+            Err(error::PatchError::DuplicateField(path)) if path.0 == vec![1]
+        ));
+    }
+
+    #[test]
+    fn interpretations() {
+        use interpret::{AnnotatedValue, Interpretation};
+
+        let mut inner = Map::new();
+        inner.insert(1, "a".into());
+        inner.insert(2, 150.into());
+        let mut pb = Map::new();
+        pb.insert(1, inner.clone().into());
+        pb.insert(2, "hello world".into());
+        pb.insert(3, ProtoData::Bytes(vec![0xff, 0xfe, 0x80]));
+        pb.insert(4, vec![ProtoData::Varint(300), ProtoData::Varint(1)].into());
+        let data = pb.encode().unwrap();
+
+        let fields = interpret::decode_annotated(&data).unwrap();
+        let kinds: Vec<_> = fields
+            .iter()
+            .map(|field| {
+                let AnnotatedValue::Len(value) = &field.value else {
+                    panic!("expected a length-delimited value");
+                };
+                let scores = value.candidates.windows(2);
+                assert!(scores.into_iter().all(|w| w[0].score >= w[1].score));
+                match &value.best().interpretation {
+                    Interpretation::Message(_) => "message",
+                    Interpretation::String(_) => "string",
+                    Interpretation::Packed(_) => "packed",
+                    Interpretation::Bytes => "bytes",
+                }
+            })
+            .collect();
+        assert_eq!(kinds, vec!["message", "string", "bytes", "packed"]);
+        assert_eq!(fields[1].offset, 2 + inner.encoded_len());
+
+        let mut map = interpret::to_map(&fields);
+        assert_eq!(map.get(&1), Some(&ProtoData::Message(inner)));
+        assert_eq!(map.remove(&4), pb.remove(&4));
+
+        // override the guess for the packed field with the raw bytes
+        let mut fields = fields;
+        let AnnotatedValue::Len(value) = &mut fields[3].value else {
+            unreachable!()
+        };
+        let index = value
+            .candidates
+            .iter()
+            .position(|c| matches!(c.interpretation, Interpretation::Bytes))
+            .unwrap();
+        let count = value.candidates.len();
+        assert!(!value.select(count));
+        assert_eq!(value.candidates.len(), count);
+        assert!(value.select(index));
+        assert_eq!(
+            fields[3].value.to_proto_data(),
+            ProtoData::Bytes(vec![0xac, 0x02, 0x01])
+        );
+
+        // "(a-abcd" parses as field 5 twice, once as varint and once as fixed32, which an
+        // encoder would not write, so the text wins over the well-numbered message
+        let value = interpret::interpret(b"(a-abcd", 0);
+        assert_eq!(
+            value.best().interpretation,
+            Interpretation::String("(a-abcd")
+        );
+        assert!(matches!(
+            value.candidates[1].interpretation,
+            Interpretation::Message(_)
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn infer_schema() {
+        use schema::{DescriptorPool, FieldType, Label};
+
+        let samples: Vec<_> = (0..10i64)
+            .map(|i| {
+                let mut point = Map::new();
+                point.insert(1, ProtoData::Fix32((i as f32 * 1.5).to_bits() as i32));
+                point.insert(2, ProtoData::Varint((i % 2) as u64));
+                let mut pb = Map::new();
+                pb.insert(1, ProtoData::Varint((-i * 1000) as u64));
+                pb.insert(
+                    2,
+                    ProtoData::Repeated(vec![point.clone().into(), point.into()]),
+                );
+                pb.insert(3, format!("sample {}", i).into());
+                pb.insert(4, vec![ProtoData::Varint(300), ProtoData::Varint(1)].into());
+                pb.encode().unwrap()
+            })
+            .collect();
+        let file = schema::infer_schema(&samples, "test", "Sample").unwrap();
+        let msg = &file.messages[0];
+        let types: Vec<_> = msg
+            .fields
+            .iter()
+            .map(|f| (f.number, f.label, f.field_type.clone()))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                (1, Label::Singular, FieldType::Int32),
+                (
+                    2,
+                    Label::Repeated,
+                    FieldType::Message("test.Sample.Field2".into())
+                ),
+                (3, Label::Singular, FieldType::String),
+                (4, Label::Repeated, FieldType::Int32),
+            ]
+        );
+        let nested: Vec<_> = msg.messages[0]
+            .fields
+            .iter()
+            .map(|f| f.field_type.clone())
+            .collect();
+        assert_eq!(nested, vec![FieldType::Float, FieldType::Bool]);
+
+        // the written schema parses back to the same descriptors, nested types included
+        let mut parsed = schema::parse(&file.to_proto()).unwrap();
+        parsed.name = file.name.clone();
+        let mut pool = DescriptorPool::new();
+        pool.add_file(parsed).unwrap();
+        assert_eq!(pool.file("sample.proto"), Some(&file));
+        let mut pool = DescriptorPool::new();
+        pool.add_source("sample.proto", &file.to_proto()).unwrap();
+        assert_eq!(
+            pool.to_json("test.Sample", &samples[1]).unwrap(),
+            r#"{"field1": -1000, "field2": [{"field1": 1.5, "field2": true}, {"field1": 1.5, "field2": true}], "field3": "sample 1", "field4": [300, 1]}"#
+        );
+
+        assert!(schema::infer_schema([&[0x0b][..]], "test", "Sample").is_err());
+
+        // evenly spread non-negative values are plain ints, mostly odd ones are zigzag negatives
+        let infer = |values: &[u64]| {
+            let samples = values.iter().map(|&v| {
+                let mut pb = Map::new();
+                pb.insert(1, ProtoData::Varint(v));
+                pb.encode().unwrap()
+            });
+            let file = schema::infer_schema(samples, "test", "Sample").unwrap();
+            file.messages[0].fields[0].field_type.clone()
+        };
+        for range in [0..10, 1..20, 0..100, 100..200, 1000..1009] {
+            assert_eq!(infer(&range.collect::<Vec<_>>()), FieldType::Int32);
+        }
+        let negatives: Vec<_> = (-10i64..-1)
+            .chain([4])
+            .map(|v| ((v << 1) ^ (v >> 63)) as u64)
+            .collect();
+        assert_eq!(infer(&negatives), FieldType::Sint32);
+        assert_eq!(infer(&[1, 301, 3, 5, 7, 9, 11, 13]), FieldType::Int32);
+
+        // a repeated field only ever written unpacked keeps that encoding
+        let samples = (0..4u8).map(|i| [0x08, i, 0x08, i + 1]);
+        let file = schema::infer_schema(samples, "test", "Sample").unwrap();
+        let field = &file.messages[0].fields[0];
+        assert_eq!((field.label, field.packed), (Label::Repeated, Some(false)));
+        assert!(file
+            .to_proto()
+            .contains("repeated int32 field_1 = 1 [packed = false];"));
+        assert_eq!(msg.fields[3].packed, None);
+
+        // as many varints as fixed64 values, varints win the tie
+        let samples = [&[0x08, 0x05][..], &[0x09, 5, 0, 0, 0, 0, 0, 0, 0][..]];
+        let file = schema::infer_schema(samples, "test", "Sample").unwrap();
+        assert_eq!(file.messages[0].fields[0].field_type, FieldType::Int32);
+
+        // packed floats, doubles and fixed integers
+        let floats =
+            hex::decode("0a0ccdcccc3e000030400000a0bf12109a9999999999d93f0000000000002340")
+                .unwrap();
+        let file = schema::infer_schema([floats], "test", "Sample").unwrap();
+        assert!(file
+            .to_proto()
+            .contains("  repeated float field_1 = 1;\n  repeated double field_2 = 2;\n"));
+        let samples = (0..4u32).map(|i| {
+            let mut pb = Map::new();
+            let values = [i, i + 300, 70_000].map(u32::to_le_bytes).concat();
+            pb.insert(1, ProtoData::Bytes(values));
+            pb.encode().unwrap()
+        });
+        let file = schema::infer_schema(samples, "test", "Sample").unwrap();
+        let field = &file.messages[0].fields[0];
+        assert_eq!(
+            (field.label, field.field_type.clone()),
+            (Label::Repeated, FieldType::Fixed32)
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn schema_parse() {
+        use schema::{FieldType, Label};
+
+        let src = r#"
+            syntax = "proto3";
+            package demo;
+            message User {
+                .demo.User.Address address = 1;
+                repeated .demo.User.Address history = 2;
+                message Address { string street = 1; }
+            }
+        "#;
+        let file = schema::parse(src).unwrap();
+        let fields: Vec<_> = file.messages[0]
+            .fields
+            .iter()
+            .map(|f| (f.label, f.field_type.clone()))
+            .collect();
+        let address = FieldType::Named(".demo.User.Address".into());
+        assert_eq!(
+            fields,
+            vec![
+                (Label::Singular, address.clone()),
+                (Label::Repeated, address)
+            ]
+        );
+
+        let mut pool = schema::DescriptorPool::new();
+        pool.add_source("demo.proto", src).unwrap();
+        let user = pool.message("demo.User").unwrap();
+        let address = FieldType::Message("demo.User.Address".into());
+        assert_eq!(user.fields[0].field_type, address);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn schema_json() {
+        let mut pool = schema::DescriptorPool::new();
+        pool.add_source(
+            "demo.proto",
+            r#"
+            syntax = "proto3";
+            package demo;
+            import "google/protobuf/any.proto";
+            import "google/protobuf/duration.proto";
+            import "google/protobuf/field_mask.proto";
+            import "google/protobuf/struct.proto";
+            import "google/protobuf/timestamp.proto";
+            import "google/protobuf/wrappers.proto";
+            message Job {
+                string job_name = 1;
+                State state = 2;
+                repeated State history = 3;
+                google.protobuf.Duration timeout = 4;
+                google.protobuf.Struct labels = 5;
+                google.protobuf.Int64Value retries = 6;
+                google.protobuf.FieldMask mask = 7;
+                repeated google.protobuf.Any details = 8;
+                map<int32, bytes> blobs = 9;
+                double ratio = 10;
+            }
+            message Owner { string name = 1; }
+            enum State { STATE_UNKNOWN = 0; STATE_DONE = 1; }
+            "#,
+        )
+        .unwrap();
+
+        let message = |fields: Vec<(u64, ProtoData)>| {
+            let mut pb = Map::new();
+            pb.extend(fields);
+            ProtoData::Message(pb)
+        };
+        let encode = |v: ProtoData| match v {
+            ProtoData::Message(pb) => pb.encode().unwrap(),
+            _ => unreachable!(),
+        };
+        let value = |field, v| message(vec![(field, v)]);
+        let any = |url: &str, v: Vec<u8>| message(vec![(1, url.into()), (2, v.into())]);
+
+        let list = value(1, vec![value(3, "a".into()), value(1, 0.into())].into());
+        let labels = message(vec![(
+            1,
+            vec![
+                message(vec![(1, "env".into()), (2, value(3, "prod".into()))]),
+                message(vec![(1, "tags".into()), (2, value(6, list))]),
+                message(vec![(1, "n".into()), (2, value(2, 1.5f64.into()))]),
+            ]
+            .into(),
+        )]);
+        let owner = encode(value(1, "ops".into()));
+        let duration = encode(message(vec![
+            (1, ProtoData::Varint(-1i64 as u64)),
+            (2, ProtoData::Varint(-500_000_000i64 as u64)),
+        ]));
+        let job = encode(message(vec![
+            (1, "nightly \u{1}".into()),
+            (2, 1.into()),
+            (3, ProtoData::Bytes(vec![0, 1, 7])),
+            (4, value(1, 30.into())),
+            (5, labels),
+            (6, value(1, ProtoData::Varint(3))),
+            (
+                7,
+                value(
+                    1,
+                    ProtoData::Repeated(vec!["job_name".into(), "timeout.seconds".into()]),
+                ),
+            ),
+            (
+                8,
+                vec![
+                    any("type.googleapis.com/demo.Owner", owner),
+                    any("type.googleapis.com/google.protobuf.Duration", duration),
+                    any("type.googleapis.com/demo.Missing", vec![1, 2]),
+                ]
+                .into(),
+            ),
+            (
+                9,
+                vec![
+                    message(vec![(1, 2.into()), (2, vec![0xffu8, 0].into())]),
+                    message(vec![(2, vec![1u8].into())]),
+                ]
+                .into(),
+            ),
+            (10, f64::NAN.into()),
+            (99, 5.into()),
+        ]));
+        assert_eq!(
+            pool.to_json("demo.Job", &job).unwrap(),
+            concat!(
+                r#"{"jobName": "nightly \u0001", "state": "STATE_DONE", "#,
+                r#""history": ["STATE_UNKNOWN", "STATE_DONE", 7], "timeout": "30s", "#,
+                r#""labels": {"env": "prod", "tags": ["a", null], "n": 1.5}, "retries": "3", "#,
+                r#""mask": "jobName,timeout.seconds", "details": ["#,
+                r#"{"@type": "type.googleapis.com/demo.Owner", "name": "ops"}, "#,
+                r#"{"@type": "type.googleapis.com/google.protobuf.Duration", "value": "-1.500s"}, "#,
+                r#"{"@type": "type.googleapis.com/demo.Missing", "value": "AQI="}], "#,
+                r#""blobs": {"2": "/wA=", "0": "AQ=="}, "ratio": "NaN", "99": 5}"#
+            )
+        );
+
+        // timestamps and durations outside of the canonical range are rejected
+        let time = |name: &str, seconds: i64, nanos: i32| {
+            let data = encode(message(vec![
+                (1, ProtoData::Varint(seconds as u64)),
+                (2, ProtoData::Varint(nanos as i64 as u64)),
+            ]));
+            match pool.to_json(&format!("google.protobuf.{}", name), &data) {
+                Ok(json) => Ok(json),
+                Err(error::SchemaError::Decode(error::DecodeError::InvalidFieldValue(n))) => Err(n),
+                Err(e) => panic!("{}", e),
+            }
+        };
+        let max = 253_402_300_799;
+        assert_eq!(
+            time("Timestamp", max, 0),
+            Ok(r#""9999-12-31T23:59:59Z""#.into())
+        );
+        assert_eq!(
+            time("Timestamp", -62_135_596_800, 0),
+            Ok(r#""0001-01-01T00:00:00Z""#.into())
+        );
+        assert_eq!(time("Timestamp", max + 1, 0), Err(1));
+        assert_eq!(time("Timestamp", -62_135_596_801, 0), Err(1));
+        assert_eq!(time("Timestamp", 0, -1), Err(2));
+        assert_eq!(
+            time("Duration", -315_576_000_000, 0),
+            Ok(r#""-315576000000s""#.into())
+        );
+        assert_eq!(time("Duration", 315_576_000_001, 0), Err(1));
+        assert_eq!(time("Duration", 1, -1), Err(2));
+        assert_eq!(time("Duration", -1, 1), Err(2));
+        assert_eq!(time("Duration", 0, -1), Ok(r#""-0.000000001s""#.into()));
+
+        assert!(matches!(
+            pool.to_json("demo.Nope", &[]),
+            Err(error::SchemaError::UnknownType(_))
+        ));
+        assert!(matches!(
+            pool.to_json("demo.Job", &encode(value(2, "x".into()))),
+            Err(error::SchemaError::Decode(
+                error::DecodeError::UnexpectedWireType { field: 2, .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn writer() {
+        // bodies of 1, 2 and 3 length bytes, nested inside each other
+        let mut expected = Map::new();
+        expected.insert(1, ProtoData::Bytes(vec![7; 20000]));
+        let mut writer = buffer::Writer::new();
+        writer.begin_message(3).unwrap();
+        writer.begin_message(2).unwrap();
+        writer.write_field(1, &vec![7; 20000].into()).unwrap();
+        writer.end_message().unwrap();
+        writer
+            .write_field(4, &vec![0.into(), 1.into(), ProtoData::Varint(2)].into())
+            .unwrap();
+        writer.begin_message(5).unwrap();
+        writer.end_message().unwrap();
+        assert_eq!(writer.depth(), 1);
+        writer.end_message().unwrap();
+        writer
+            .write_field(6, &vec![ProtoData::Varint(1), 2.into()].into())
+            .unwrap();
+
+        let mut middle = Map::new();
+        middle.insert(2, expected.into());
+        middle.insert(4, vec![0.into(), 1.into(), ProtoData::Varint(2)].into());
+        middle.insert(5, Map::new().into());
+        let mut pb = Map::new();
+        pb.insert(3, middle.into());
+        pb.insert(6, vec![ProtoData::Varint(1), 2.into()].into());
+
+        let mut reference = Vec::new();
+        pb.get(&3).unwrap().encode_to(3, &mut reference).unwrap();
+        pb.get(&6).unwrap().encode_to(6, &mut reference).unwrap();
+        assert_eq!(writer.into_inner().unwrap(), reference);
+
+        let mut writer = buffer::Writer::new();
+        assert!(matches!(
+            writer.end_message(),
+            Err(error::EncodeError::NoOpenMessage)
+        ));
+        writer.begin_message(1).unwrap();
+        assert!(matches!(
+            writer.into_inner(),
+            Err(error::EncodeError::UnclosedMessage(1))
+        ));
+    }
+
+    #[test]
+    fn reader() {
+        let data: Vec<u8> = (0..10).collect();
+        let mut buf = Reader::new(data.as_slice());
+        assert_eq!(buf.peek_byte().unwrap(), 0);
+        assert_eq!(buf.position(), 0);
+        buf.skip(2).unwrap();
+        buf.mark();
+        assert_eq!(buf.read_byte().unwrap(), 2);
+        buf.rewind();
+        assert_eq!(buf.position(), 2);
+
+        let mut sub = buf.limit(5).unwrap();
+        assert_eq!((sub.start(), sub.end()), (2, 7));
+        sub.seek(5).unwrap();
+        assert_eq!(sub.read_byte().unwrap(), 5);
+        let mut nested = sub.limit(1).unwrap();
+        assert_eq!(nested.position(), 6);
+        assert_eq!(nested.read_byte().unwrap(), 6);
+        assert!(nested.peek_byte().is_err());
+        assert!(sub.seek(1).is_err());
+        assert!(sub.seek(8).is_err());
+        assert!(sub.limit(1).is_err());
+
+        assert_eq!(buf.position(), 7);
+        assert!(buf.limit(4).is_err());
+        buf.seek(9).unwrap();
+        assert_eq!(buf.read_byte().unwrap(), 9);
     }
 
     #[test]
@@ -143,6 +1262,26 @@ mod tests {
                 fixint::read_fix32(&mut Reader::new(data.as_slice())).unwrap()
             )
         }
+
+        let mut data = Vec::new();
+        fixint::write_ufix32(u32::MAX, &mut data).unwrap();
+        fixint::write_ufix64(u64::MAX - 1, &mut data).unwrap();
+        fixint::write_float(-1.5, &mut data).unwrap();
+        fixint::write_double(f64::MIN_POSITIVE, &mut data).unwrap();
+        fixint::write_fixed_be(0x01020304u32, &mut data).unwrap();
+        assert_eq!(data.len(), 4 + 8 + 4 + 8 + 4);
+        assert_eq!(&data[data.len() - 4..], &[1, 2, 3, 4]);
+
+        let mut buf = Reader::new(data.as_slice());
+        assert_eq!(fixint::read_ufix32(&mut buf).unwrap(), u32::MAX);
+        assert_eq!(fixint::read_ufix64(&mut buf).unwrap(), u64::MAX - 1);
+        assert_eq!(fixint::read_float(&mut buf).unwrap(), -1.5);
+        assert_eq!(fixint::read_double(&mut buf).unwrap(), f64::MIN_POSITIVE);
+        assert_eq!(
+            fixint::read_fixed_be::<u32, _>(&mut buf).unwrap(),
+            0x01020304
+        );
+        assert!(fixint::read_fixed::<u32, _>(&mut buf).is_err());
     }
 
     #[test]
@@ -167,6 +1306,73 @@ mod tests {
             123456,
             varint::read_uvarint(&mut Reader::new(data.as_slice())).unwrap()
         );
+
+        // the unrolled path with padding and the byte loop without must agree
+        for num in [0, 127, 128, 1 << 35, u64::MAX >> 1, u64::MAX] {
+            let data = varint::encode_uvarint(num);
+            let mut padded = data.clone();
+            padded.resize(data.len() + varint::MAX_VARINT_LENGTH, 0);
+            assert_eq!(varint::decode_uvarint(&data).unwrap(), (num, data.len()));
+            assert_eq!(varint::decode_uvarint(&padded).unwrap(), (num, data.len()));
+        }
+        let overflow = [0xff; 11];
+        assert!(matches!(
+            varint::decode_uvarint(&overflow),
+            Err(error::DecodeError::OverFlow64Bit)
+        ));
+        assert!(matches!(
+            varint::decode_uvarint(&overflow[..10]),
+            Err(error::DecodeError::OverFlow64Bit)
+        ));
+        assert!(matches!(
+            varint::decode_uvarint(&overflow[..9]),
+            Err(error::DecodeError::UnexpectedEof)
+        ));
+        assert!(matches!(
+            varint::decode_uvarint(&[]),
+            Err(error::DecodeError::EOF)
+        ));
+    }
+
+    #[test]
+    fn strict_uvarint() {
+        for num in [0, 1, 127, 128, u32::MAX as u64, u64::MAX] {
+            let data = varint::encode_uvarint(num);
+            assert_eq!(
+                varint::decode_uvarint_strict(&data).unwrap(),
+                (num, data.len())
+            );
+        }
+        for data in [&[0x80, 0x00][..], &[0xff, 0x80, 0x80, 0x00]] {
+            assert!(varint::decode_uvarint(data).is_ok());
+            assert!(matches!(
+                varint::decode_uvarint_strict(data),
+                Err(error::DecodeError::OverlongVarint)
+            ));
+        }
+        let mut data = varint::encode_uvarint(u64::MAX);
+        data[9] = 0x02;
+        assert!(varint::decode_uvarint(&data).is_ok());
+        assert!(matches!(
+            varint::decode_uvarint_strict(&data),
+            Err(error::DecodeError::OverFlow64Bit)
+        ));
+
+        let data = varint::encode_uvarint(u32::MAX as u64);
+        let mut buf = Reader::new(data.as_slice());
+        assert_eq!(varint::read_uvarint32(&mut buf).unwrap(), u32::MAX);
+        assert!(buf.is_end());
+        let data = varint::encode_uvarint(u32::MAX as u64 + 1);
+        let mut buf = Reader::new(data.as_slice());
+        assert!(matches!(
+            varint::read_uvarint32(&mut buf),
+            Err(error::DecodeError::OverFlow32Bit)
+        ));
+        assert_eq!(buf.remaining(), data.len());
+        assert!(matches!(
+            varint::read_uvarint32_strict(&mut Reader::new([0x81, 0x00])),
+            Err(error::DecodeError::OverlongVarint)
+        ));
     }
 
     #[test]
@@ -0,0 +1,378 @@
+//! encode, decode and convert singular, optional and repeated fields of a given [`Kind`]
+use crate::buffer::Reader;
+use crate::error::{DecodeError, EncodeError};
+use crate::message::kind::Kind;
+use crate::protobuf::{decode_protobuf, read_tag, Map, ProtoData, WireType};
+use crate::varint::{read_uvarint, write_uvarint};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+#[inline]
+fn write_tag<W>(field: u64, wire_type: WireType, buf: &mut W) -> Result<(), EncodeError>
+where
+    W: Write,
+{
+    write_uvarint((field << 3) | (wire_type as u64), buf)
+}
+
+#[inline]
+fn check_wire_type<K>(field: u64, wire_type: WireType) -> Result<(), DecodeError>
+where
+    K: Kind,
+{
+    if wire_type != K::WIRE_TYPE {
+        return Err(DecodeError::UnexpectedWireType {
+            field,
+            expected: K::WIRE_TYPE,
+            actual: wire_type,
+        });
+    }
+    Ok(())
+}
+
+/// write a singular field, default values are skipped
+pub fn encode<K, W>(field: u64, v: &K::Value, buf: &mut W) -> Result<(), EncodeError>
+where
+    K: Kind,
+    W: Write,
+{
+    if K::is_default(v) {
+        return Ok(());
+    }
+    write_tag(field, K::WIRE_TYPE, buf)?;
+    K::write(v, buf)
+}
+
+/// write an optional field if it is set
+pub fn encode_optional<K, W>(
+    field: u64,
+    v: &Option<K::Value>,
+    buf: &mut W,
+) -> Result<(), EncodeError>
+where
+    K: Kind,
+    W: Write,
+{
+    if let Some(v) = v {
+        write_tag(field, K::WIRE_TYPE, buf)?;
+        K::write(v, buf)?;
+    }
+    Ok(())
+}
+
+/// write a repeated field, numeric kinds are packed
+pub fn encode_repeated<K, W>(field: u64, v: &[K::Value], buf: &mut W) -> Result<(), EncodeError>
+where
+    K: Kind,
+    W: Write,
+{
+    if v.is_empty() {
+        return Ok(());
+    }
+    if K::WIRE_TYPE == WireType::LEN {
+        for i in v {
+            write_tag(field, K::WIRE_TYPE, buf)?;
+            K::write(i, buf)?;
+        }
+        return Ok(());
+    }
+    let mut packed = Vec::new();
+    for i in v {
+        K::write(i, &mut packed)?;
+    }
+    write_tag(field, WireType::LEN, buf)?;
+    write_uvarint(packed.len() as u64, buf)?;
+    buf.write_all(packed.as_slice())?;
+    Ok(())
+}
+
+/// read a singular field, the last scalar occurrence wins and messages are merged
+pub fn merge<K, T>(
+    field: u64,
+    wire_type: WireType,
+    v: &mut K::Value,
+    buf: &mut Reader<T>,
+) -> Result<(), DecodeError>
+where
+    K: Kind,
+    T: AsRef<[u8]>,
+{
+    check_wire_type::<K>(field, wire_type)?;
+    K::merge(v, buf)
+}
+
+/// read an optional field, like [`merge`] once it is set
+pub fn merge_optional<K, T>(
+    field: u64,
+    wire_type: WireType,
+    v: &mut Option<K::Value>,
+    buf: &mut Reader<T>,
+) -> Result<(), DecodeError>
+where
+    K: Kind,
+    T: AsRef<[u8]>,
+{
+    check_wire_type::<K>(field, wire_type)?;
+    match v {
+        Some(v) => K::merge(v, buf),
+        None => {
+            *v = Some(K::read(buf)?);
+            Ok(())
+        }
+    }
+}
+
+/// read one element of a repeated field, or all elements of a packed one
+pub fn merge_repeated<K, T>(
+    field: u64,
+    wire_type: WireType,
+    v: &mut Vec<K::Value>,
+    buf: &mut Reader<T>,
+) -> Result<(), DecodeError>
+where
+    K: Kind,
+    T: AsRef<[u8]>,
+{
+    if wire_type == WireType::LEN && K::WIRE_TYPE != WireType::LEN {
+        let len = read_uvarint(buf)?;
+        let mut packed = buf.limit(len as usize)?;
+        while !packed.is_end() {
+            v.push(K::read(&mut packed)?);
+        }
+        return Ok(());
+    }
+    check_wire_type::<K>(field, wire_type)?;
+    v.push(K::read(buf)?);
+    Ok(())
+}
+
+/// skip the value of an unknown field
+pub fn skip<T>(wire_type: WireType, buf: &mut Reader<T>) -> Result<(), DecodeError>
+where
+    T: AsRef<[u8]>,
+{
+    match wire_type {
+        WireType::VARINT => {
+            read_uvarint(buf)?;
+        }
+        WireType::I64 => buf.skip(8)?,
+        WireType::I32 => buf.skip(4)?,
+        WireType::LEN => {
+            let len = read_uvarint(buf)?;
+            buf.skip(len as usize)?
+        }
+        x => return Err(DecodeError::DeprecatedWireType(x)),
+    }
+    Ok(())
+}
+
+/// convert a singular field, returns `None` for the default value
+pub fn to_data<K>(v: K::Value) -> Option<ProtoData>
+where
+    K: Kind,
+{
+    if K::is_default(&v) {
+        return None;
+    }
+    Some(K::to_data(v))
+}
+
+/// convert an optional field
+pub fn optional_to_data<K>(v: Option<K::Value>) -> Option<ProtoData>
+where
+    K: Kind,
+{
+    v.map(K::to_data)
+}
+
+/// convert a repeated field, returns `None` if it is empty
+pub fn repeated_to_data<K>(v: Vec<K::Value>) -> Option<ProtoData>
+where
+    K: Kind,
+{
+    match v.len() {
+        0 => None,
+        1 => v.into_iter().next().map(K::to_data),
+        _ => Some(ProtoData::Repeated(v.into_iter().map(K::to_data).collect())),
+    }
+}
+
+/// convert a singular field, a field which occurs several times takes the last value
+pub fn from_data<K>(field: u64, data: Option<ProtoData>) -> Result<K::Value, DecodeError>
+where
+    K: Kind,
+{
+    Ok(optional_from_data::<K>(field, data)?.unwrap_or_default())
+}
+
+/// convert an optional field
+pub fn optional_from_data<K>(
+    field: u64,
+    data: Option<ProtoData>,
+) -> Result<Option<K::Value>, DecodeError>
+where
+    K: Kind,
+{
+    let data = match data {
+        Some(ProtoData::Repeated(mut v)) => v.pop(),
+        data => data,
+    };
+    match data {
+        Some(data) => match K::from_data(data) {
+            Some(v) => Ok(Some(v)),
+            None => Err(DecodeError::InvalidFieldValue(field)),
+        },
+        None => Ok(None),
+    }
+}
+
+/// convert a repeated field, packed scalars decoded as bytes or string are unpacked
+pub fn repeated_from_data<K>(
+    field: u64,
+    data: Option<ProtoData>,
+) -> Result<Vec<K::Value>, DecodeError>
+where
+    K: Kind,
+{
+    let items = match data {
+        Some(ProtoData::Repeated(v)) => v,
+        Some(data) => vec![data],
+        None => return Ok(Vec::new()),
+    };
+    let mut result = Vec::with_capacity(items.len());
+    for item in items {
+        let packed = match item {
+            ProtoData::Bytes(v) if K::WIRE_TYPE != WireType::LEN => v,
+            ProtoData::String(v) if K::WIRE_TYPE != WireType::LEN => v.into_bytes(),
+            ProtoData::Message(v) if K::WIRE_TYPE != WireType::LEN => v
+                .encode()
+                .map_err(|_| DecodeError::InvalidFieldValue(field))?,
+            item => {
+                match K::from_data(item) {
+                    Some(v) => result.push(v),
+                    None => return Err(DecodeError::InvalidFieldValue(field)),
+                }
+                continue;
+            }
+        };
+        let mut buf = Reader::new(packed.as_slice());
+        while !buf.is_end() {
+            result.push(K::read(&mut buf)?);
+        }
+    }
+    Ok(result)
+}
+
+/// write a map field as repeated `{1: key, 2: value}` entries
+pub fn encode_map<K, V, W>(
+    field: u64,
+    v: &BTreeMap<K::Value, V::Value>,
+    buf: &mut W,
+) -> Result<(), EncodeError>
+where
+    K: Kind,
+    V: Kind,
+    W: Write,
+{
+    let mut entry = Vec::new();
+    for (key, value) in v {
+        entry.clear();
+        write_tag(1, K::WIRE_TYPE, &mut entry)?;
+        K::write(key, &mut entry)?;
+        write_tag(2, V::WIRE_TYPE, &mut entry)?;
+        V::write(value, &mut entry)?;
+        write_tag(field, WireType::LEN, buf)?;
+        write_uvarint(entry.len() as u64, buf)?;
+        buf.write_all(entry.as_slice())?;
+    }
+    Ok(())
+}
+
+/// read one entry of a map field
+pub fn merge_map<K, V, T>(
+    field: u64,
+    wire_type: WireType,
+    v: &mut BTreeMap<K::Value, V::Value>,
+    buf: &mut Reader<T>,
+) -> Result<(), DecodeError>
+where
+    K: Kind,
+    K::Value: Ord,
+    V: Kind,
+    T: AsRef<[u8]>,
+{
+    if wire_type != WireType::LEN {
+        return Err(DecodeError::UnexpectedWireType {
+            field,
+            expected: WireType::LEN,
+            actual: wire_type,
+        });
+    }
+    let len = read_uvarint(buf)?;
+    let mut entry = buf.limit(len as usize)?;
+    let mut key = K::Value::default();
+    let mut value = V::Value::default();
+    while !entry.is_end() {
+        match read_tag(&mut entry)? {
+            (1, wire_type) => merge::<K, _>(field, wire_type, &mut key, &mut entry)?,
+            (2, wire_type) => merge::<V, _>(field, wire_type, &mut value, &mut entry)?,
+            (_, wire_type) => skip(wire_type, &mut entry)?,
+        }
+    }
+    v.insert(key, value);
+    Ok(())
+}
+
+/// convert a map field to repeated entry messages, returns `None` if it is empty
+pub fn map_to_data<K, V>(v: BTreeMap<K::Value, V::Value>) -> Option<ProtoData>
+where
+    K: Kind,
+    V: Kind,
+{
+    let entries = v
+        .into_iter()
+        .map(|(key, value)| {
+            let mut entry = Map::new();
+            entry.insert(1, K::to_data(key));
+            entry.insert(2, V::to_data(value));
+            ProtoData::Message(entry)
+        })
+        .collect::<Vec<_>>();
+    match entries.len() {
+        0 => None,
+        1 => entries.into_iter().next(),
+        _ => Some(ProtoData::Repeated(entries)),
+    }
+}
+
+/// convert repeated entry messages to a map field
+pub fn map_from_data<K, V>(
+    field: u64,
+    data: Option<ProtoData>,
+) -> Result<BTreeMap<K::Value, V::Value>, DecodeError>
+where
+    K: Kind,
+    K::Value: Ord,
+    V: Kind,
+{
+    let entries = match data {
+        Some(ProtoData::Repeated(v)) => v,
+        Some(data) => vec![data],
+        None => return Ok(BTreeMap::new()),
+    };
+    let mut result = BTreeMap::new();
+    for entry in entries {
+        let mut entry = match entry {
+            ProtoData::Message(v) => v,
+            ProtoData::Bytes(v) => match decode_protobuf(v)? {
+                ProtoData::Message(v) => v,
+                _ => return Err(DecodeError::InvalidFieldValue(field)),
+            },
+            _ => return Err(DecodeError::InvalidFieldValue(field)),
+        };
+        let key = from_data::<K>(field, entry.remove(&1))?;
+        let value = from_data::<V>(field, entry.remove(&2))?;
+        result.insert(key, value);
+    }
+    Ok(result)
+}
@@ -0,0 +1,353 @@
+//! protobuf field types, one marker type per `.proto` scalar type plus [`Message`]
+use crate::buffer::Reader;
+use crate::error::{DecodeError, EncodeError};
+use crate::fixint::{read_fixed, write_fixed};
+use crate::message::ProtoMessage;
+use crate::protobuf::{ProtoData, WireType};
+use crate::varint::{read_uvarint, read_varint, write_uvarint, write_varint};
+use std::io::Write;
+use std::marker::PhantomData;
+
+/// how a rust value is written on the wire and represented in a [`ProtoData`] tree
+pub trait Kind {
+    type Value: Default;
+
+    const WIRE_TYPE: WireType;
+
+    /// default values of singular fields are not written
+    fn is_default(v: &Self::Value) -> bool;
+
+    /// write the value without tag
+    fn write<W>(v: &Self::Value, buf: &mut W) -> Result<(), EncodeError>
+    where
+        W: Write;
+
+    /// read the value, the tag has already been consumed
+    fn read<T>(buf: &mut Reader<T>) -> Result<Self::Value, DecodeError>
+    where
+        T: AsRef<[u8]>;
+
+    /// read another occurrence of a singular field into the current value, scalars are
+    /// replaced and messages merged
+    fn merge<T>(v: &mut Self::Value, buf: &mut Reader<T>) -> Result<(), DecodeError>
+    where
+        T: AsRef<[u8]>,
+    {
+        *v = Self::read(buf)?;
+        Ok(())
+    }
+
+    fn to_data(v: Self::Value) -> ProtoData;
+
+    /// returns `None` if the data has the wrong variant
+    fn from_data(data: ProtoData) -> Option<Self::Value>;
+}
+
+macro_rules! impl_varint_kind {
+    ($($name:ident($t:ty): |$v:ident| $to_raw:expr, |$raw:ident| $from_raw:expr;)*) => {
+        $(
+            pub struct $name;
+
+            impl $name {
+                #[inline]
+                fn to_raw($v: $t) -> u64 {
+                    $to_raw
+                }
+
+                #[inline]
+                fn from_raw($raw: u64) -> $t {
+                    $from_raw
+                }
+            }
+
+            impl Kind for $name {
+                type Value = $t;
+
+                const WIRE_TYPE: WireType = WireType::VARINT;
+
+                fn is_default(v: &$t) -> bool {
+                    *v == <$t>::default()
+                }
+
+                fn write<W>(v: &$t, buf: &mut W) -> Result<(), EncodeError>
+                where
+                    W: Write,
+                {
+                    write_uvarint(Self::to_raw(*v), buf)
+                }
+
+                fn read<T>(buf: &mut Reader<T>) -> Result<$t, DecodeError>
+                where
+                    T: AsRef<[u8]>,
+                {
+                    Ok(Self::from_raw(read_uvarint(buf)?))
+                }
+
+                fn to_data(v: $t) -> ProtoData {
+                    ProtoData::Varint(Self::to_raw(v))
+                }
+
+                fn from_data(data: ProtoData) -> Option<$t> {
+                    match data {
+                        ProtoData::Varint(v) => Some(Self::from_raw(v)),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_varint_kind! {
+    Int32(i32): |v| v as i64 as u64, |v| v as i32;
+    Int64(i64): |v| v as u64, |v| v as i64;
+    Uint32(u32): |v| v as u64, |v| v as u32;
+    Uint64(u64): |v| v, |v| v;
+    Bool(bool): |v| v as u64, |v| v != 0;
+}
+
+macro_rules! impl_zigzag_kind {
+    ($($name:ident($t:ty);)*) => {
+        $(
+            pub struct $name;
+
+            impl Kind for $name {
+                type Value = $t;
+
+                const WIRE_TYPE: WireType = WireType::VARINT;
+
+                fn is_default(v: &$t) -> bool {
+                    *v == 0
+                }
+
+                fn write<W>(v: &$t, buf: &mut W) -> Result<(), EncodeError>
+                where
+                    W: Write,
+                {
+                    write_varint(*v as i64, buf)
+                }
+
+                fn read<T>(buf: &mut Reader<T>) -> Result<$t, DecodeError>
+                where
+                    T: AsRef<[u8]>,
+                {
+                    Ok(read_varint(buf)? as $t)
+                }
+
+                fn to_data(v: $t) -> ProtoData {
+                    let v = v as i64;
+                    ProtoData::Varint(((v << 1) ^ (v >> 63)) as u64)
+                }
+
+                fn from_data(data: ProtoData) -> Option<$t> {
+                    match data {
+                        ProtoData::Varint(v) => Some((((v >> 1) as i64) ^ -((v & 1) as i64)) as $t),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_zigzag_kind! {
+    Sint32(i32);
+    Sint64(i64);
+}
+
+macro_rules! impl_fixed_kind {
+    ($($name:ident($t:ty): $variant:ident($raw:ty), $wire_type:ident;)*) => {
+        $(
+            pub struct $name;
+
+            impl Kind for $name {
+                type Value = $t;
+
+                const WIRE_TYPE: WireType = WireType::$wire_type;
+
+                fn is_default(v: &$t) -> bool {
+                    *v == <$t>::default()
+                }
+
+                fn write<W>(v: &$t, buf: &mut W) -> Result<(), EncodeError>
+                where
+                    W: Write,
+                {
+                    write_fixed(*v, buf)
+                }
+
+                fn read<T>(buf: &mut Reader<T>) -> Result<$t, DecodeError>
+                where
+                    T: AsRef<[u8]>,
+                {
+                    read_fixed(buf)
+                }
+
+                fn to_data(v: $t) -> ProtoData {
+                    ProtoData::$variant(<$raw>::from_le_bytes(v.to_le_bytes()))
+                }
+
+                fn from_data(data: ProtoData) -> Option<$t> {
+                    match data {
+                        ProtoData::$variant(v) => Some(<$t>::from_le_bytes(v.to_le_bytes())),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_fixed_kind! {
+    Fixed32(u32): Fix32(i32), I32;
+    Sfixed32(i32): Fix32(i32), I32;
+    Float(f32): Fix32(i32), I32;
+    Fixed64(u64): Fix64(i64), I64;
+    Sfixed64(i64): Fix64(i64), I64;
+    Double(f64): Fix64(i64), I64;
+}
+
+pub struct String;
+
+impl Kind for String {
+    type Value = std::string::String;
+
+    const WIRE_TYPE: WireType = WireType::LEN;
+
+    fn is_default(v: &Self::Value) -> bool {
+        v.is_empty()
+    }
+
+    fn write<W>(v: &Self::Value, buf: &mut W) -> Result<(), EncodeError>
+    where
+        W: Write,
+    {
+        Bytes::write_slice(v.as_bytes(), buf)
+    }
+
+    fn read<T>(buf: &mut Reader<T>) -> Result<Self::Value, DecodeError>
+    where
+        T: AsRef<[u8]>,
+    {
+        let len = read_uvarint(buf)?;
+        Ok(std::str::from_utf8(buf.read_bytes(len as usize)?)?.to_string())
+    }
+
+    fn to_data(v: Self::Value) -> ProtoData {
+        ProtoData::String(v)
+    }
+
+    fn from_data(data: ProtoData) -> Option<Self::Value> {
+        match data {
+            ProtoData::String(v) => Some(v),
+            ProtoData::Bytes(v) => std::string::String::from_utf8(v).ok(),
+            ProtoData::Message(v) if v.is_empty() => Some(Self::Value::new()),
+            ProtoData::Message(v) => std::string::String::from_utf8(v.encode().ok()?).ok(),
+            _ => None,
+        }
+    }
+}
+
+pub struct Bytes;
+
+impl Bytes {
+    fn write_slice<W>(v: &[u8], buf: &mut W) -> Result<(), EncodeError>
+    where
+        W: Write,
+    {
+        write_uvarint(v.len() as u64, buf)?;
+        buf.write_all(v)?;
+        Ok(())
+    }
+}
+
+impl Kind for Bytes {
+    type Value = Vec<u8>;
+
+    const WIRE_TYPE: WireType = WireType::LEN;
+
+    fn is_default(v: &Self::Value) -> bool {
+        v.is_empty()
+    }
+
+    fn write<W>(v: &Self::Value, buf: &mut W) -> Result<(), EncodeError>
+    where
+        W: Write,
+    {
+        Bytes::write_slice(v.as_slice(), buf)
+    }
+
+    fn read<T>(buf: &mut Reader<T>) -> Result<Self::Value, DecodeError>
+    where
+        T: AsRef<[u8]>,
+    {
+        let len = read_uvarint(buf)?;
+        Ok(buf.read_bytes(len as usize)?.to_vec())
+    }
+
+    fn to_data(v: Self::Value) -> ProtoData {
+        ProtoData::Bytes(v)
+    }
+
+    fn from_data(data: ProtoData) -> Option<Self::Value> {
+        match data {
+            ProtoData::Bytes(v) => Some(v),
+            ProtoData::String(v) => Some(v.into_bytes()),
+            ProtoData::Message(v) => v.encode().ok(),
+            _ => None,
+        }
+    }
+}
+
+/// an embedded message
+pub struct Message<M>(PhantomData<M>);
+
+impl<M> Kind for Message<M>
+where
+    M: ProtoMessage,
+{
+    type Value = M;
+
+    const WIRE_TYPE: WireType = WireType::LEN;
+
+    fn is_default(_: &M) -> bool {
+        false
+    }
+
+    fn write<W>(v: &M, buf: &mut W) -> Result<(), EncodeError>
+    where
+        W: Write,
+    {
+        Bytes::write_slice(v.encode()?.as_slice(), buf)
+    }
+
+    fn read<T>(buf: &mut Reader<T>) -> Result<M, DecodeError>
+    where
+        T: AsRef<[u8]>,
+    {
+        let len = read_uvarint(buf)?;
+        M::decode(buf.read_bytes(len as usize)?)
+    }
+
+    fn merge<T>(v: &mut M, buf: &mut Reader<T>) -> Result<(), DecodeError>
+    where
+        T: AsRef<[u8]>,
+    {
+        let len = read_uvarint(buf)?;
+        v.merge(&mut buf.limit(len as usize)?)
+    }
+
+    fn to_data(v: M) -> ProtoData {
+        ProtoData::Message(v.into_map())
+    }
+
+    fn from_data(data: ProtoData) -> Option<M> {
+        let map = match data {
+            ProtoData::Message(v) => v,
+            ProtoData::Bytes(v) => return M::decode(v).ok(),
+            ProtoData::String(v) => return M::decode(v).ok(),
+            _ => return None,
+        };
+        M::from_map(map).ok()
+    }
+}
@@ -0,0 +1,154 @@
+//! typed messages on top of the wire format
+//!
+//! [`ProtoMessage`] is usually implemented with `#[derive(ProtoMessage)]` from the `derive`
+//! feature, the generated code calls the helpers in [`field`] with a [`kind::Kind`] per field.
+use crate::buffer::Reader;
+use crate::error::{DecodeError, EncodeError};
+use crate::protobuf::{read_tag, Map, ProtoData, WireType};
+use std::io::Write;
+
+pub mod field;
+pub mod kind;
+mod unknown;
+
+#[cfg(feature = "derive")]
+pub use protobuf_lite_derive::ProtoMessage;
+pub use unknown::UnknownFields;
+
+/// a message with a fixed set of fields
+///
+/// example
+/// ```
+/// use protobuf_lite::buffer::Reader;
+/// use protobuf_lite::error::{DecodeError, EncodeError};
+/// use protobuf_lite::message::{field, kind, ProtoMessage};
+/// use protobuf_lite::protobuf::{Map, ProtoData, WireType};
+/// use std::io::Write;
+///
+/// #[derive(Default, Debug, PartialEq)]
+/// struct Request {
+///     id: u64,
+/// }
+///
+/// impl ProtoMessage for Request {
+///     fn encode_to<W: Write>(&self, buf: &mut W) -> Result<(), EncodeError> {
+///         field::encode::<kind::Uint64, _>(1, &self.id, buf)
+///     }
+///
+///     fn merge_field<T: AsRef<[u8]>>(
+///         &mut self,
+///         field: u64,
+///         wire_type: WireType,
+///         buf: &mut Reader<T>,
+///     ) -> Result<(), DecodeError> {
+///         match field {
+///             1 => field::merge::<kind::Uint64, _>(field, wire_type, &mut self.id, buf),
+///             _ => field::skip(wire_type, buf),
+///         }
+///     }
+///
+///     fn into_map(self) -> Map<u64, ProtoData> {
+///         let mut map = Map::new();
+///         if let Some(v) = field::to_data::<kind::Uint64>(self.id) {
+///             map.insert(1, v);
+///         }
+///         map
+///     }
+///
+///     fn from_map(mut map: Map<u64, ProtoData>) -> Result<Self, DecodeError> {
+///         Ok(Request {
+///             id: field::from_data::<kind::Uint64>(1, map.remove(&1))?,
+///         })
+///     }
+/// }
+///
+/// fn main() {
+///     let req = Request { id: 150 };
+///     let data = req.encode().unwrap();
+///     assert_eq!(data, vec![0x08, 0x96, 0x01]);
+///     assert_eq!(Request::decode(&data).unwrap(), req);
+/// }
+/// ```
+pub trait ProtoMessage: Sized + Default {
+    /// write all fields of the message
+    fn encode_to<W>(&self, buf: &mut W) -> Result<(), EncodeError>
+    where
+        W: Write;
+
+    /// read the value of one field, the tag has already been consumed
+    fn merge_field<T>(
+        &mut self,
+        field: u64,
+        wire_type: WireType,
+        buf: &mut Reader<T>,
+    ) -> Result<(), DecodeError>
+    where
+        T: AsRef<[u8]>;
+
+    /// convert into the dynamic representation
+    fn into_map(self) -> Map<u64, ProtoData>;
+
+    /// convert from the dynamic representation, missing fields get their default value
+    fn from_map(map: Map<u64, ProtoData>) -> Result<Self, DecodeError>;
+
+    /// encode the message
+    fn encode(&self) -> Result<Vec<u8>, EncodeError> {
+        let mut buf = Vec::new();
+        self.encode_to(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// read fields until the end of the buffer and merge them into the message
+    fn merge<T>(&mut self, buf: &mut Reader<T>) -> Result<(), DecodeError>
+    where
+        T: AsRef<[u8]>,
+    {
+        while !buf.is_end() {
+            let (field, wire_type) = read_tag(buf)?;
+            self.merge_field(field, wire_type, buf)?;
+        }
+        Ok(())
+    }
+
+    /// decode a message
+    fn decode<T>(data: T) -> Result<Self, DecodeError>
+    where
+        T: AsRef<[u8]>,
+    {
+        let mut msg = Self::default();
+        msg.merge(&mut Reader::new(data.as_ref()))?;
+        Ok(msg)
+    }
+}
+
+impl<M> ProtoMessage for Box<M>
+where
+    M: ProtoMessage,
+{
+    fn encode_to<W>(&self, buf: &mut W) -> Result<(), EncodeError>
+    where
+        W: Write,
+    {
+        M::encode_to(self, buf)
+    }
+
+    fn merge_field<T>(
+        &mut self,
+        field: u64,
+        wire_type: WireType,
+        buf: &mut Reader<T>,
+    ) -> Result<(), DecodeError>
+    where
+        T: AsRef<[u8]>,
+    {
+        M::merge_field(self, field, wire_type, buf)
+    }
+
+    fn into_map(self) -> Map<u64, ProtoData> {
+        M::into_map(*self)
+    }
+
+    fn from_map(map: Map<u64, ProtoData>) -> Result<Self, DecodeError> {
+        M::from_map(map).map(Box::new)
+    }
+}
@@ -0,0 +1,237 @@
+use crate::buffer::{BufMut, Reader};
+use crate::error::{DecodeError, EncodeError};
+use crate::fixint::{read_fix32, read_fix64};
+use crate::message::field::skip;
+use crate::protobuf::{read_tag, Map, ProtoData, WireType};
+use crate::varint::{decode_uvarint, read_uvarint, write_uvarint, MAX_VARINT_LENGTH};
+use std::collections::btree_map::Entry;
+
+/// fields of a typed message which are not part of its schema
+///
+/// the tag and value bytes of every field are kept as they were read and in wire order, so
+/// encoding writes the same bytes again, including overlong varints, the order across field
+/// numbers and groups. [`UnknownFields::get`] and [`UnknownFields::into_map`] decode them on
+/// demand: varints and fixed values by value and length-delimited values as raw
+/// [`ProtoData::Bytes`], without guessing whether they are strings or messages.
+///
+/// example
+/// ```
+/// use protobuf_lite::buffer::Reader;
+/// use protobuf_lite::message::UnknownFields;
+/// use protobuf_lite::protobuf::{read_tag, ProtoData};
+/// fn main() {
+///     let data = vec![0x08, 0x96, 0x01, 0x12, 0x02, 0x08, 0x01];
+///     let mut buf = Reader::new(data.as_slice());
+///     let mut unknown = UnknownFields::new();
+///     while !buf.is_end() {
+///         let (field, wire_type) = read_tag(&mut buf).unwrap();
+///         unknown.merge_field(field, wire_type, &mut buf).unwrap();
+///     }
+///     assert_eq!(unknown.get(1), Some(ProtoData::Varint(150)));
+///     assert_eq!(unknown.get(2), Some(ProtoData::Bytes(vec![0x08, 0x01])));
+///     assert_eq!(unknown.encode().unwrap(), data);
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct UnknownFields {
+    data: Vec<u8>,
+}
+
+impl UnknownFields {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// read encoded fields, all of them are unknown
+    pub fn decode<T>(data: T) -> Result<Self, DecodeError>
+    where
+        T: AsRef<[u8]>,
+    {
+        let mut buf = Reader::new(data.as_ref());
+        let mut unknown = Self::new();
+        while !buf.is_end() {
+            let (field, wire_type) = read_tag(&mut buf)?;
+            unknown.merge_field(field, wire_type, &mut buf)?;
+        }
+        Ok(unknown)
+    }
+
+    /// read the raw value of a field, the tag has already been consumed
+    ///
+    /// the tag bytes are taken from right before the value when they encode the same tag, so a
+    /// non-canonical tag is kept as well
+    pub fn merge_field<T>(
+        &mut self,
+        field: u64,
+        wire_type: WireType,
+        buf: &mut Reader<T>,
+    ) -> Result<(), DecodeError>
+    where
+        T: AsRef<[u8]>,
+    {
+        let tag = (field << 3) | wire_type as u64;
+        let len = self.data.len();
+        let consumed = buf.consumed_slice();
+        match raw_tag(consumed, tag) {
+            Some(raw) => self.data.extend_from_slice(raw),
+            None => {
+                let _ = write_uvarint(tag, &mut self.data);
+            }
+        }
+        let start = consumed.len();
+        if let Err(e) = skip_value(field, wire_type, buf) {
+            self.data.truncate(len);
+            return Err(e);
+        }
+        self.data.extend_from_slice(&buf.consumed_slice()[start..]);
+        Ok(())
+    }
+
+    /// add a value to a field after the existing ones
+    pub fn push(&mut self, field: u64, data: &ProtoData) -> Result<(), EncodeError> {
+        match data {
+            ProtoData::Repeated(list) => {
+                for data in list {
+                    data.encode_to(field, &mut self.data)?;
+                }
+                Ok(())
+            }
+            data => data.encode_to(field, &mut self.data),
+        }
+    }
+
+    /// write the fields as they were read
+    pub fn encode_to<W>(&self, buf: &mut W) -> Result<(), EncodeError>
+    where
+        W: BufMut + ?Sized,
+    {
+        buf.put_slice(&self.data)
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, EncodeError> {
+        Ok(self.data.clone())
+    }
+
+    /// gets the encoded fields
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// gets the values of a field, several occurrences as [`ProtoData::Repeated`]
+    pub fn get(&self, field: u64) -> Option<ProtoData> {
+        self.to_map().remove(&field)
+    }
+
+    /// decode every field, groups have no [`ProtoData`] form and are left out
+    pub fn to_map(&self) -> Map<u64, ProtoData> {
+        let mut map = Map::new();
+        let mut buf = Reader::new(self.data.as_slice());
+        while !buf.is_end() {
+            let (field, data) = match read_field(&mut buf) {
+                Ok(Some(v)) => v,
+                Ok(None) => continue,
+                // the fields were checked when they were read
+                Err(_) => break,
+            };
+            match map.entry(field) {
+                Entry::Occupied(mut entry) => match entry.get_mut() {
+                    ProtoData::Repeated(list) => list.push(data),
+                    existing => *existing = ProtoData::Repeated(vec![existing.clone(), data]),
+                },
+                Entry::Vacant(entry) => {
+                    entry.insert(data);
+                }
+            }
+        }
+        map
+    }
+
+    pub fn into_map(self) -> Map<u64, ProtoData> {
+        self.to_map()
+    }
+
+    /// encode the fields of a map ordered by field number, a value which can not be encoded is
+    /// an invalid field value
+    pub fn from_map(map: Map<u64, ProtoData>) -> Result<Self, DecodeError> {
+        let mut unknown = Self::new();
+        for (&field, data) in map.iter() {
+            unknown
+                .push(field, data)
+                .map_err(|_| DecodeError::InvalidFieldValue(field))?;
+        }
+        Ok(unknown)
+    }
+}
+
+/// the bytes at the end of `consumed` which encode `tag`, a shorter suffix can not decode to
+/// the same tag as a longer one
+fn raw_tag(consumed: &[u8], tag: u64) -> Option<&[u8]> {
+    (1..=consumed.len().min(MAX_VARINT_LENGTH))
+        .map(|n| &consumed[consumed.len() - n..])
+        .find(|raw| matches!(decode_uvarint(raw), Ok((v, n)) if v == tag && n == raw.len()))
+}
+
+/// skip a value, a group up to its matching end tag
+#[allow(deprecated)]
+fn skip_value<T>(field: u64, wire_type: WireType, buf: &mut Reader<T>) -> Result<(), DecodeError>
+where
+    T: AsRef<[u8]>,
+{
+    if wire_type != WireType::SGROUP {
+        return skip(wire_type, buf);
+    }
+    loop {
+        match read_tag(buf)? {
+            (end, WireType::EGROUP) if end == field => return Ok(()),
+            (_, WireType::EGROUP) => return Err(DecodeError::InvalidFieldValue(field)),
+            (nested, wire_type) => skip_value(nested, wire_type, buf)?,
+        }
+    }
+}
+
+/// read one field, `None` for a group
+fn read_field<T>(buf: &mut Reader<T>) -> Result<Option<(u64, ProtoData)>, DecodeError>
+where
+    T: AsRef<[u8]>,
+{
+    let (field, wire_type) = read_tag(buf)?;
+    let data = match wire_type {
+        WireType::VARINT => ProtoData::Varint(read_uvarint(buf)?),
+        WireType::I64 => ProtoData::Fix64(read_fix64(buf)?),
+        WireType::I32 => ProtoData::Fix32(read_fix32(buf)?),
+        WireType::LEN => {
+            let len = read_uvarint(buf)?;
+            ProtoData::Bytes(buf.read_bytes(len as usize)?.to_vec())
+        }
+        wire_type => {
+            skip_value(field, wire_type, buf)?;
+            return Ok(None);
+        }
+    };
+    Ok(Some((field, data)))
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for UnknownFields {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serde_bytes::serialize(&self.data, serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for UnknownFields {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let data = <serde_bytes::ByteBuf as serde::Deserialize>::deserialize(deserializer)?;
+        Self::decode(data).map_err(serde::de::Error::custom)
+    }
+}
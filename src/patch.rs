@@ -0,0 +1,177 @@
+//! edit fields of an encoded message in place, length prefixes of the enclosing messages are
+//! rewritten and all other bytes are kept as they are
+use crate::buffer::Reader;
+use crate::error::{DecodeError, FieldPath, PatchError};
+use crate::event::{Field, Fields, Value};
+use crate::protobuf::ProtoData;
+use crate::varint::{decode_uvarint, encode_uvarint};
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// length prefix of an enclosing message
+struct Parent {
+    prefix: Range<usize>,
+    len: usize,
+}
+
+/// find the body of the message at `path`
+///
+/// every step must occur once, a decoder merges the occurrences of a message field so there
+/// is no single body to edit
+fn resolve(data: &[u8], path: &[u64]) -> Result<(Vec<Parent>, Range<usize>), PatchError> {
+    let mut parents = Vec::with_capacity(path.len());
+    let mut body = 0..data.len();
+    for (depth, &number) in path.iter().enumerate() {
+        let field = match find_fields(data, &body, number)?.as_slice() {
+            [] => {
+                return Err(PatchError::FieldNotFound(FieldPath(
+                    path[..=depth].to_vec(),
+                )))
+            }
+            [field] => *field,
+            _ => {
+                return Err(PatchError::DuplicateField(FieldPath(
+                    path[..=depth].to_vec(),
+                )))
+            }
+        };
+        if !matches!(field.value, Value::Len(_)) {
+            return Err(PatchError::NotAMessage(FieldPath(path[..=depth].to_vec())));
+        }
+        let (_, tag_len) = decode_uvarint(&data[field.offset..])?;
+        parents.push(Parent {
+            prefix: field.offset + tag_len..field.value_offset,
+            len: field.end - field.value_offset,
+        });
+        body = field.value_offset..field.end;
+    }
+    Ok((parents, body))
+}
+
+fn fields<'a>(data: &'a [u8], body: &Range<usize>) -> Fields<'a> {
+    Fields::from_reader(Reader::with_offset(&data[body.clone()], body.start))
+}
+
+/// every occurrence of a field in wire order, the whole message must be well formed
+fn find_fields<'a>(
+    data: &'a [u8],
+    body: &Range<usize>,
+    number: u64,
+) -> Result<Vec<Field<'a>>, DecodeError> {
+    let mut found = Vec::new();
+    for field in fields(data, body) {
+        let field = field?;
+        if field.number == number {
+            found.push(field);
+        }
+    }
+    Ok(found)
+}
+
+/// remove the spans in reverse order and return the change in length
+fn remove_spans(data: &mut Vec<u8>, spans: &[Range<usize>]) -> isize {
+    let mut delta = 0;
+    for span in spans.iter().rev() {
+        delta -= span.len() as isize;
+        data.drain(span.clone());
+    }
+    delta
+}
+
+/// rewrite the length prefixes after the innermost body changed by `delta` bytes
+fn fix_prefixes(data: &mut Vec<u8>, parents: &[Parent], mut delta: isize) {
+    // inner prefixes come after outer ones, so rewriting them first keeps the outer positions
+    for parent in parents.iter().rev() {
+        let prefix = encode_uvarint((parent.len as isize + delta) as u64);
+        delta += prefix.len() as isize - parent.prefix.len() as isize;
+        data.splice(parent.prefix.clone(), prefix);
+    }
+}
+
+fn split_path(path: &[u64]) -> Result<(&[u64], u64), PatchError> {
+    match path.split_last() {
+        Some((&number, parents)) => Ok((parents, number)),
+        None => Err(PatchError::EmptyPath),
+    }
+}
+
+fn encode(number: u64, value: &ProtoData) -> Result<Vec<u8>, PatchError> {
+    let mut buf = Vec::with_capacity(value.encoded_len(number));
+    value.encode_to(number, &mut buf)?;
+    Ok(buf)
+}
+
+/// replace a field, the last occurrence is overwritten and earlier ones are removed
+///
+/// decoders take the last occurrence of a singular field, so patching only one of several
+/// would have no effect. a repeated field is replaced as a whole, pass
+/// [`ProtoData::Repeated`] to keep more than one element. the messages on the path must occur
+/// once, otherwise [`PatchError::DuplicateField`] is returned
+///
+/// example
+/// ```
+/// use protobuf_lite::patch::patch_field;
+/// use protobuf_lite::protobuf::Map;
+/// fn main() {
+///     let mut header = Map::new();
+///     header.insert(1, 42.into());
+///     let mut pb = Map::new();
+///     pb.insert(1, header.clone().into());
+///     pb.insert(2, "body".into());
+///     let mut data = pb.encode().unwrap();
+///
+///     patch_field(&mut data, &[1, 1], &"a much longer value".into()).unwrap();
+///     header.insert(1, "a much longer value".into());
+///     pb.insert(1, header.into());
+///     assert_eq!(data, pb.encode().unwrap());
+/// }
+/// ```
+pub fn patch_field(data: &mut Vec<u8>, path: &[u64], value: &ProtoData) -> Result<(), PatchError> {
+    let (parent_path, number) = split_path(path)?;
+    let (parents, body) = resolve(data, parent_path)?;
+    let mut spans = find_fields(data, &body, number)?
+        .iter()
+        .map(|f| f.offset..f.end)
+        .collect::<Vec<_>>();
+    let last = spans
+        .pop()
+        .ok_or_else(|| PatchError::FieldNotFound(FieldPath(path.to_vec())))?;
+    let encoded = encode(number, value)?;
+    let mut delta = encoded.len() as isize - last.len() as isize;
+    // the last occurrence comes after the earlier ones, so replacing it first keeps their spans
+    data.splice(last, encoded);
+    delta += remove_spans(data, &spans);
+    fix_prefixes(data, &parents, delta);
+    Ok(())
+}
+
+/// append a field at the end of the message at the parent path, which must occur once
+pub fn insert_field(data: &mut Vec<u8>, path: &[u64], value: &ProtoData) -> Result<(), PatchError> {
+    let (parent_path, number) = split_path(path)?;
+    let (parents, body) = resolve(data, parent_path)?;
+    // the message has to be well formed before anything is appended to it
+    for field in fields(data, &body) {
+        field?;
+    }
+    let encoded = encode(number, value)?;
+    let delta = encoded.len() as isize;
+    data.splice(body.end..body.end, encoded);
+    fix_prefixes(data, &parents, delta);
+    Ok(())
+}
+
+/// remove every occurrence of a field and return how many were removed, the messages on the
+/// path must occur once
+pub fn delete_field(data: &mut Vec<u8>, path: &[u64]) -> Result<usize, PatchError> {
+    let (parent_path, number) = split_path(path)?;
+    let (parents, body) = resolve(data, parent_path)?;
+    let spans = find_fields(data, &body, number)?
+        .iter()
+        .map(|f| f.offset..f.end)
+        .collect::<Vec<_>>();
+    let delta = remove_spans(data, &spans);
+    if !spans.is_empty() {
+        fix_prefixes(data, &parents, delta);
+    }
+    Ok(spans.len())
+}
@@ -1,21 +1,23 @@
-use crate::buffer::Reader;
-use crate::error::DecodeError;
-use crate::error::EncodeError::DataError;
-use crate::fixint::{read_fix32, read_fix64, write_fix32, write_fix64};
+use crate::buffer::{BufMut, Reader, SliceWriter};
+use crate::error::{DecodeError, EncodeError, FieldPath};
+use crate::event::{Fields, Value};
+use crate::fixint::{write_fix32, write_fix64};
 use crate::json;
-use crate::varint::{read_uvarint, write_uvarint};
-use anyhow::Result;
-use std::collections::btree_map::Entry;
-use std::collections::BTreeMap;
-use std::fmt::{Display, Formatter};
-use std::hash::{Hash, Hasher};
-use std::io::Write;
-use std::mem::discriminant;
-use std::ops::{Deref, DerefMut};
-use std::str;
+use crate::varint::{read_uvarint, uvarint_len, write_uvarint};
+use alloc::collections::btree_map::Entry;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+use core::hash::{Hash, Hasher};
+use core::mem::discriminant;
+use core::ops::{Deref, DerefMut};
+use core::str;
 
 #[repr(u8)]
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WireType {
     VARINT = 0, // int32, int64, uint32, uint64, sint32, sint64, bool, enum
     I64 = 1,    // fixed64, sfixed64, double
@@ -28,7 +30,7 @@ pub enum WireType {
 }
 
 impl Display for WireType {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         #[allow(deprecated)]
         match self {
             WireType::VARINT => {
@@ -53,12 +55,21 @@ impl Display for WireType {
     }
 }
 
+/// decoded protobuf value
+///
+/// with the `serde` feature the value is serialized externally tagged with the variant name in
+/// snake case, so every round-trip keeps the wire representation:
+/// `{"varint": 1}`, `{"fix64": -1}`, `{"fix32": 1}`, `{"bytes": [1, 2]}`, `{"string": "a"}`,
+/// `{"repeated": [...]}` and `{"message": {"1": ...}}`.
+/// `Bytes` is written as a byte string for formats which support it (MessagePack, CBOR)
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum ProtoData {
     Varint(u64),
     Fix64(i64),
     Fix32(i32),
-    Bytes(Vec<u8>),
+    Bytes(#[cfg_attr(feature = "serde", serde(with = "serde_bytes"))] Vec<u8>),
     String(String),
     Repeated(Vec<ProtoData>),
     Message(Map<u64, ProtoData>),
@@ -74,9 +85,115 @@ impl ProtoData {
         }
     }
 
-    pub fn encode_to<T>(&self, field: u64, buf: &mut T) -> Result<()>
+    /// gets the name of the variant, used in error messages
+    pub fn variant_name(&self) -> &'static str {
+        match self {
+            ProtoData::Varint(_) => "varint",
+            ProtoData::Fix64(_) => "fix64",
+            ProtoData::Fix32(_) => "fix32",
+            ProtoData::Bytes(_) => "bytes",
+            ProtoData::String(_) => "string",
+            ProtoData::Repeated(_) => "repeated",
+            ProtoData::Message(_) => "message",
+        }
+    }
+
+    /// write the value with its tag, the value is validated before anything is written
+    pub fn encode_to<T>(&self, field: u64, buf: &mut T) -> Result<(), EncodeError>
+    where
+        T: BufMut + ?Sized,
+    {
+        self.check().map_err(|e| with_parent(e, field))?;
+        self.write_to(field, buf)
+    }
+
+    /// write one element of a repeated field, varint and fixed values are written without tag
+    pub fn encode_repeated_to<T>(&self, field: u64, buf: &mut T) -> Result<(), EncodeError>
+    where
+        T: BufMut + ?Sized,
+    {
+        match self {
+            ProtoData::Varint(_) | ProtoData::Fix64(_) | ProtoData::Fix32(_) => {
+                self.write_repeated_to(field, buf)
+            }
+            ProtoData::Repeated(_) => Ok(()),
+            v => v.encode_to(field, buf),
+        }
+    }
+
+    /// find the first problem [`ProtoData::collect_errors`] would push without allocating,
+    /// the path of the error is relative to this value
+    fn check(&self) -> Result<(), EncodeError> {
+        match self {
+            ProtoData::Repeated(v) => {
+                let expected = v.iter().find(|i| !matches!(i, ProtoData::Repeated(_)));
+                for (index, i) in v.iter().enumerate() {
+                    match (i, expected) {
+                        (ProtoData::Repeated(_), _) => {
+                            return Err(EncodeError::NestedRepeated {
+                                path: FieldPath::default(),
+                                index,
+                            })
+                        }
+                        (i, Some(expected)) if discriminant(i) != discriminant(expected) => {
+                            return Err(EncodeError::MixedRepeated {
+                                path: FieldPath::default(),
+                                index,
+                                expected: expected.variant_name(),
+                                actual: i.variant_name(),
+                            })
+                        }
+                        (ProtoData::Message(m), _) => {
+                            m.check().map_err(|e| with_parent(e, index as u64))?
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(())
+            }
+            ProtoData::Message(v) => v.check(),
+            _ => Ok(()),
+        }
+    }
+
+    /// push a problem for every repeated element which can not be encoded,
+    /// `path` ends with the field number of this value
+    fn collect_errors(&self, path: &mut Vec<u64>, errors: &mut Vec<EncodeError>) {
+        match self {
+            ProtoData::Repeated(v) => {
+                // the first element which is not itself repeated decides the expected type
+                let expected = v.iter().find(|i| !matches!(i, ProtoData::Repeated(_)));
+                for (index, i) in v.iter().enumerate() {
+                    match (i, expected) {
+                        (ProtoData::Repeated(_), _) => errors.push(EncodeError::NestedRepeated {
+                            path: FieldPath(path.clone()),
+                            index,
+                        }),
+                        (i, Some(expected)) if discriminant(i) != discriminant(expected) => errors
+                            .push(EncodeError::MixedRepeated {
+                                path: FieldPath(path.clone()),
+                                index,
+                                expected: expected.variant_name(),
+                                actual: i.variant_name(),
+                            }),
+                        (ProtoData::Message(m), _) => {
+                            path.push(index as u64);
+                            m.collect_errors(path, errors);
+                            path.pop();
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            ProtoData::Message(v) => v.collect_errors(path, errors),
+            _ => {}
+        }
+    }
+
+    /// write the value with its tag without validating it
+    fn write_to<T>(&self, field: u64, buf: &mut T) -> Result<(), EncodeError>
     where
-        T: Write,
+        T: BufMut + ?Sized,
     {
         match self {
             ProtoData::Varint(v) => {
@@ -94,48 +211,38 @@ impl ProtoData {
             ProtoData::Bytes(v) => {
                 write_uvarint((field << 3) | (self.wire_type() as u64), buf)?;
                 write_uvarint(v.len() as u64, buf)?;
-                buf.write_all(v.as_slice())?;
+                buf.put_slice(v.as_slice())?;
             }
             ProtoData::String(v) => {
                 write_uvarint((field << 3) | (self.wire_type() as u64), buf)?;
                 write_uvarint(v.len() as u64, buf)?;
-                buf.write_all(v.as_bytes())?;
+                buf.put_slice(v.as_bytes())?;
             }
             ProtoData::Repeated(v) => {
-                let mut typ = None;
-                if v.is_empty() {
+                let Some(first) = v.first() else {
                     return Ok(());
-                }
-                match v[0].wire_type() {
-                    WireType::LEN => {}
-                    _ => {
-                        write_uvarint((field << 3) | (self.wire_type() as u64), buf)?;
-                    }
+                };
+                // varint and fixed values are packed into one length-delimited record
+                if first.wire_type() != WireType::LEN {
+                    write_uvarint((field << 3) | (self.wire_type() as u64), buf)?;
+                    write_uvarint(Self::packed_len(v) as u64, buf)?;
                 }
                 for i in v {
-                    let disc = discriminant(i);
-                    if let Some(existing) = typ {
-                        if existing != disc {
-                            return Err(DataError.into());
-                        }
-                    } else {
-                        typ = Some(disc);
-                    }
-
-                    if matches!(*i, ProtoData::Repeated(_)) {
-                        return Err(DataError.into());
-                    }
-                    i.encode_repeated_to(field, buf)?
+                    i.write_repeated_to(field, buf)?
                 }
             }
-            ProtoData::Message(v) => v.encode_to(buf)?,
+            ProtoData::Message(v) => {
+                write_uvarint((field << 3) | (self.wire_type() as u64), buf)?;
+                write_uvarint(v.encoded_len() as u64, buf)?;
+                v.write_to(buf)?
+            }
         }
         Ok(())
     }
 
-    pub fn encode_repeated_to<T>(&self, field: u64, buf: &mut T) -> Result<()>
+    fn write_repeated_to<T>(&self, field: u64, buf: &mut T) -> Result<(), EncodeError>
     where
-        T: Write,
+        T: BufMut + ?Sized,
     {
         match self {
             ProtoData::Varint(v) => {
@@ -145,21 +252,59 @@ impl ProtoData {
                 write_fix64(*v, buf)?;
             }
             ProtoData::Fix32(v) => write_fix32(*v, buf)?,
-            ProtoData::Bytes(v) => {
-                write_uvarint((field << 3) | (self.wire_type() as u64), buf)?;
-                write_uvarint(v.len() as u64, buf)?;
-                buf.write_all(v.as_slice())?;
-            }
-            ProtoData::String(v) => {
-                write_uvarint((field << 3) | (self.wire_type() as u64), buf)?;
-                write_uvarint(v.len() as u64, buf)?;
-                buf.write_all(v.as_bytes())?;
-            }
             ProtoData::Repeated(_) => {}
-            ProtoData::Message(v) => v.encode_to(buf)?,
+            v => v.write_to(field, buf)?,
         }
         Ok(())
     }
+
+    /// gets the number of bytes [`ProtoData::encode_to`] writes, tags included
+    ///
+    /// example
+    /// ```
+    /// use protobuf_lite::protobuf::ProtoData;
+    /// fn main() {
+    ///     let data = ProtoData::from("hello");
+    ///     let mut buf = Vec::new();
+    ///     data.encode_to(1, &mut buf).unwrap();
+    ///     assert_eq!(data.encoded_len(1), buf.len());
+    /// }
+    /// ```
+    pub fn encoded_len(&self, field: u64) -> usize {
+        let tag_len = uvarint_len(field << 3);
+        match self {
+            ProtoData::Repeated(v) => match v.first().map(|i| i.wire_type()) {
+                None => 0,
+                Some(WireType::LEN) => v.iter().map(|i| i.encoded_len(field)).sum(),
+                Some(_) => {
+                    let len = Self::packed_len(v);
+                    tag_len + uvarint_len(len as u64) + len
+                }
+            },
+            v => tag_len + v.value_len(),
+        }
+    }
+
+    /// length of the value without tag, length-delimited values include their length prefix
+    fn value_len(&self) -> usize {
+        match self {
+            ProtoData::Varint(v) => uvarint_len(*v),
+            ProtoData::Fix64(_) => size_of::<i64>(),
+            ProtoData::Fix32(_) => size_of::<i32>(),
+            ProtoData::Bytes(v) => uvarint_len(v.len() as u64) + v.len(),
+            ProtoData::String(v) => uvarint_len(v.len() as u64) + v.len(),
+            ProtoData::Repeated(_) => 0,
+            ProtoData::Message(v) => {
+                let len = v.encoded_len();
+                uvarint_len(len as u64) + len
+            }
+        }
+    }
+
+    /// length of packed varint and fixed values
+    fn packed_len(v: &[ProtoData]) -> usize {
+        v.iter().map(|i| i.value_len()).sum()
+    }
 }
 
 macro_rules! impl_from {
@@ -199,13 +344,13 @@ impl From<bool> for ProtoData {
 
 impl From<f32> for ProtoData {
     fn from(v: f32) -> Self {
-        Self::Fix32(i32::from_le_bytes(v.to_le_bytes()))
+        Self::Fix32(v.to_bits() as i32)
     }
 }
 
 impl From<f64> for ProtoData {
     fn from(v: f64) -> Self {
-        Self::Fix64(i64::from_le_bytes(v.to_le_bytes()))
+        Self::Fix64(v.to_bits() as i64)
     }
 }
 
@@ -218,7 +363,7 @@ impl From<&str> for ProtoData {
 impl_from!(Vec<u8> => Bytes, String => String, Vec<ProtoData> => Repeated, Map<u64, ProtoData> => Message);
 
 impl Display for ProtoData {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         match self {
             ProtoData::Varint(v) => {
                 write!(f, "{}", v)
@@ -315,6 +460,29 @@ impl Hash for Map<u64, ProtoData> {
     }
 }
 
+/// serialized as a map from field number to [`ProtoData`]
+#[cfg(feature = "serde")]
+impl serde::Serialize for Map<u64, ProtoData> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.map.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Map<u64, ProtoData> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(Map {
+            map: BTreeMap::deserialize(deserializer)?,
+        })
+    }
+}
+
 impl Map<u64, ProtoData> {
     pub fn new() -> Self {
         Map {
@@ -322,21 +490,122 @@ impl Map<u64, ProtoData> {
         }
     }
 
-    pub fn encode_to<T>(&self, buf: &mut T) -> Result<()>
+    /// write all fields, the message is validated before anything is written
+    pub fn encode_to<T>(&self, buf: &mut T) -> Result<(), EncodeError>
+    where
+        T: BufMut + ?Sized,
+    {
+        self.check()?;
+        self.write_to(buf)
+    }
+
+    /// check that every field can be encoded and report all problems at once,
+    /// repeated values must not be nested and all elements must have the same variant
+    ///
+    /// example
+    /// ```
+    /// use protobuf_lite::error::EncodeError;
+    /// use protobuf_lite::protobuf::{Map, ProtoData};
+    /// fn main() {
+    ///     let mut inner = Map::new();
+    ///     inner.insert(3, vec![ProtoData::Varint(1), "a".into()].into());
+    ///     let mut pb = Map::new();
+    ///     pb.insert(1, inner.into());
+    ///     let errors = pb.validate().unwrap_err();
+    ///     assert_eq!(errors.len(), 1);
+    ///     assert_eq!(
+    ///         errors[0].to_string(),
+    ///         "field 1.3: repeated element 1 is string, expected varint"
+    ///     );
+    /// }
+    /// ```
+    pub fn validate(&self) -> Result<(), Vec<EncodeError>> {
+        let mut errors = Vec::new();
+        self.collect_errors(&mut Vec::new(), &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// find the first problem of [`Map::validate`] without allocating unless there is one
+    fn check(&self) -> Result<(), EncodeError> {
+        for (&key, value) in self.iter() {
+            value.check().map_err(|e| with_parent(e, key))?;
+        }
+        Ok(())
+    }
+
+    fn collect_errors(&self, path: &mut Vec<u64>, errors: &mut Vec<EncodeError>) {
+        for (&key, value) in self.iter() {
+            path.push(key);
+            value.collect_errors(path, errors);
+            path.pop();
+        }
+    }
+
+    fn write_to<T>(&self, buf: &mut T) -> Result<(), EncodeError>
     where
-        T: Write,
+        T: BufMut + ?Sized,
     {
         for (&key, value) in self.iter() {
-            value.encode_to(key, buf)?
+            value.write_to(key, buf)?
         }
         Ok(())
     }
 
-    pub fn encode(&self) -> Result<Vec<u8>> {
-        let mut buf = Vec::new();
+    /// gets the number of bytes [`Map::encode_to`] writes
+    pub fn encoded_len(&self) -> usize {
+        self.iter()
+            .map(|(&key, value)| value.encoded_len(key))
+            .sum()
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, EncodeError> {
+        let mut buf = Vec::with_capacity(self.encoded_len());
         self.encode_to(&mut buf)?;
         Ok(buf)
     }
+
+    /// encode into the beginning of a slice and return the number of bytes written
+    ///
+    /// nothing is written if the slice is too small
+    ///
+    /// example
+    /// ```
+    /// use protobuf_lite::protobuf::Map;
+    /// fn main() {
+    ///     let mut pb = Map::new();
+    ///     pb.insert(1, 150.into());
+    ///     let mut buf = [0u8; 16];
+    ///     let n = pb.encode_into(&mut buf).unwrap();
+    ///     assert_eq!(&buf[..n], &[0x08, 0x96, 0x01]);
+    ///     assert!(pb.encode_into(&mut buf[..2]).is_err());
+    /// }
+    /// ```
+    pub fn encode_into(&self, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        let len = self.encoded_len();
+        if len > buf.len() {
+            return Err(EncodeError::BufferTooSmall {
+                required: len,
+                remaining: buf.len(),
+            });
+        }
+        let mut writer = SliceWriter::new(buf);
+        self.encode_to(&mut writer)?;
+        Ok(writer.position())
+    }
+}
+
+/// prepend a field number or element index to the path of an invalid repeated value
+fn with_parent(mut e: EncodeError, parent: u64) -> EncodeError {
+    if let EncodeError::MixedRepeated { path, .. } | EncodeError::NestedRepeated { path, .. } =
+        &mut e
+    {
+        path.0.insert(0, parent);
+    }
+    e
 }
 
 impl TryFrom<u64> for WireType {
@@ -356,7 +625,7 @@ impl TryFrom<u64> for WireType {
     }
 }
 
-pub fn read_tag<T>(buf: &mut Reader<T>) -> Result<(u64, WireType)>
+pub fn read_tag<T>(buf: &mut Reader<T>) -> Result<(u64, WireType), DecodeError>
 where
     T: AsRef<[u8]>,
 {
@@ -364,135 +633,92 @@ where
     Ok((tag >> 3, WireType::try_from(tag & 0x07)?))
 }
 
-pub fn read_length_delimited<T>(buf: &mut Reader<T>) -> Result<Vec<ProtoData>>
+pub fn read_length_delimited<T>(buf: &mut Reader<T>) -> Result<Vec<ProtoData>, DecodeError>
 where
     T: AsRef<[u8]>,
 {
-    let mut result: Vec<ProtoData> = Vec::new();
     let len = read_uvarint(buf)?;
-    if len == 0 {
-        result.push(ProtoData::Message(Map::new()));
-        return Ok(result);
-    }
+    let mut data_buf = buf.limit(len as usize)?;
+    interpret_length_delimited(&mut data_buf)
+}
 
-    let mut data_buf = Reader::new(buf.read_bytes(len as usize)?);
+/// guess the type of a length-delimited value, a message first, then a string, then bytes
+fn interpret_length_delimited(data_buf: &mut Reader<&[u8]>) -> Result<Vec<ProtoData>, DecodeError> {
+    if data_buf.is_end() {
+        return Ok(vec![ProtoData::Message(Map::new())]);
+    }
 
     // 优先protobuf
-    loop {
-        match decode_protobuf_from(&mut data_buf) {
-            Ok(v) => match v {
-                ProtoData::Message(msg) => {
-                    if msg.len() > 0 {
-                        result.push(ProtoData::Message(msg));
-                        return Ok(result);
-                    }
-                }
-                _ => {}
-            },
-            Err(err) => match err.downcast_ref::<DecodeError>() {
-                Some(DecodeError::EOF) => return Ok(result),
-                _ => {
-                    result.clear();
-                    data_buf.reset();
-                    break;
-                }
-            },
-        };
+    if let Ok(ProtoData::Message(msg)) = decode_protobuf_from(data_buf) {
+        if !msg.is_empty() {
+            return Ok(vec![ProtoData::Message(msg)]);
+        }
     }
+    data_buf.reset();
 
     // 转为str 可能会把varint也转换成str
-    match str::from_utf8(data_buf.read_all_bytes()?) {
-        Ok(v) => {
-            result.push(ProtoData::String(v.to_string()));
-            return Ok(result);
-        }
-        _ => {
-            result.clear();
-            data_buf.reset();
-        }
+    if let Ok(v) = str::from_utf8(data_buf.remaining_slice()) {
+        return Ok(vec![ProtoData::String(v.to_string())]);
     }
 
-    // 转为varint数组
-    // loop {
-    //     match read_uvarint(&mut data_buf) {
-    //         Ok(v) => result.push(DataType::Varint(v)),
-    //         Err(err) => {
-    //             if let Some(DecodeError::EOF) = err.downcast_ref::<DecodeError>() {
-    //                 return Ok(result);
-    //             } else {
-    //                 result.clear();
-    //                 data_buf.reset();
-    //                 break;
-    //             }
-    //         }
-    //     }
-    // }
-    result.push(ProtoData::Bytes(Vec::from(data_buf.read_all_bytes()?)));
-
-    Ok(result)
+    Ok(vec![ProtoData::Bytes(Vec::from(
+        data_buf.read_all_bytes()?,
+    ))])
 }
 
-pub fn decode_protobuf_hex(data: &str) -> Result<ProtoData> {
+pub fn decode_protobuf_hex(data: &str) -> Result<ProtoData, DecodeError> {
     decode_protobuf_from(&mut Reader::new(
-        hex::decode(data.replace(" ", ""))?.as_slice(),
+        hex::decode(data.replace(" ", ""))
+            .map_err(DecodeError::InvalidHex)?
+            .as_slice(),
     ))
 }
 
-pub fn decode_protobuf<T>(data: T) -> Result<ProtoData>
+pub fn decode_protobuf<T>(data: T) -> Result<ProtoData, DecodeError>
 where
     T: AsRef<[u8]>,
 {
     decode_protobuf_from(&mut Reader::new(data.as_ref()))
 }
 
-pub fn decode_protobuf_from<T>(buf: &mut Reader<T>) -> Result<ProtoData>
+pub fn decode_protobuf_from<T>(buf: &mut Reader<T>) -> Result<ProtoData, DecodeError>
 where
     T: AsRef<[u8]>,
 {
     let mut parsed_data = Map::default();
-    loop {
-        match read_tag(buf) {
-            Ok((field, wire_type)) => {
-                let data = match wire_type {
-                    WireType::VARINT => {
-                        ProtoData::Varint(read_uvarint(buf).map_err(|_| DecodeError::Error)?)
-                    }
-                    WireType::I64 => {
-                        ProtoData::Fix64(read_fix64(buf).map_err(|_| DecodeError::Error)?)
-                    }
-                    WireType::I32 => {
-                        ProtoData::Fix32(read_fix32(buf).map_err(|_| DecodeError::Error)?)
-                    }
-                    WireType::LEN => {
-                        let mut list =
-                            read_length_delimited(buf).map_err(|_| DecodeError::Error)?;
-                        match list.len() {
-                            0 => {
-                                return Err(DecodeError::Error.into());
-                            }
-                            1 => list.remove(0),
-                            _ => ProtoData::Repeated(list),
-                        }
-                    }
-                    x => return Err(DecodeError::DeprecatedWireType(x).into()),
-                };
-
-                match parsed_data.entry(field) {
-                    Entry::Occupied(mut entry) => match entry.get_mut() {
-                        ProtoData::Repeated(list) => list.push(data),
-                        existing => {
-                            *existing = ProtoData::Repeated(vec![existing.clone(), data]);
-                        }
-                    },
-                    Entry::Vacant(entry) => {
-                        entry.insert(data);
+    let remaining = buf.remaining();
+    for field in Fields::from_reader(buf.limit(remaining)?) {
+        let field = field?;
+        let data = match field.value {
+            Value::Varint(v) => ProtoData::Varint(v),
+            Value::Fix64(v) => ProtoData::Fix64(v),
+            Value::Fix32(v) => ProtoData::Fix32(v),
+            Value::Len(v) => {
+                let mut list =
+                    interpret_length_delimited(&mut Reader::with_offset(v, field.value_offset))?;
+                match list.len() {
+                    0 => {
+                        return Err(DecodeError::Error);
                     }
+                    1 => list.remove(0),
+                    _ => ProtoData::Repeated(list),
                 }
             }
-            Err(err) => match err.downcast_ref::<DecodeError>() {
-                Some(DecodeError::EOF) => break,
-                _ => return Err(err),
+            Value::StartGroup | Value::EndGroup => {
+                return Err(DecodeError::DeprecatedWireType(field.wire_type))
+            }
+        };
+
+        match parsed_data.entry(field.number) {
+            Entry::Occupied(mut entry) => match entry.get_mut() {
+                ProtoData::Repeated(list) => list.push(data),
+                existing => {
+                    *existing = ProtoData::Repeated(vec![existing.clone(), data]);
+                }
             },
+            Entry::Vacant(entry) => {
+                entry.insert(data);
+            }
         }
     }
     Ok(ProtoData::Message(parsed_data))
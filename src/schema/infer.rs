@@ -0,0 +1,392 @@
+use crate::error::DecodeError;
+use crate::interpret::{decode_annotated, AnnotatedField, AnnotatedValue, Interpretation};
+use crate::schema::{FieldDescriptor, FieldType, FileDescriptor, Label, MessageDescriptor, Syntax};
+use std::collections::BTreeMap;
+
+/// observations of one message type across all samples
+#[derive(Debug, Default)]
+struct MessageStats {
+    count: usize,
+    fields: BTreeMap<u64, FieldStats>,
+}
+
+#[derive(Debug, Default)]
+struct FieldStats {
+    /// most occurrences within one message
+    max_count: usize,
+    varints: Vec<u64>,
+    fix32: Vec<i32>,
+    fix64: Vec<i64>,
+    len: LenStats,
+}
+
+#[derive(Debug, Default)]
+struct LenStats {
+    count: usize,
+    message_viable: usize,
+    message_best: usize,
+    string_viable: usize,
+    string_best: usize,
+    packed_viable: usize,
+    packed_best: usize,
+    /// values of all occurrences which parse as packed varints
+    packed: Vec<u64>,
+    /// occurrences whose length is a multiple of 4 or 8 and their packed fixed-width values
+    fixed32_viable: usize,
+    fixed32: Vec<i32>,
+    fixed64_viable: usize,
+    fixed64: Vec<i64>,
+    nested: MessageStats,
+}
+
+impl MessageStats {
+    fn add(&mut self, fields: &[AnnotatedField<'_>]) {
+        self.count += 1;
+        let mut counts = BTreeMap::new();
+        for field in fields {
+            *counts.entry(field.number).or_insert(0) += 1;
+            let stats = self.fields.entry(field.number).or_default();
+            match &field.value {
+                AnnotatedValue::Varint(v) => stats.varints.push(*v),
+                AnnotatedValue::Fix32(v) => stats.fix32.push(*v),
+                AnnotatedValue::Fix64(v) => stats.fix64.push(*v),
+                AnnotatedValue::Len(value) => {
+                    let len = &mut stats.len;
+                    len.count += 1;
+                    let data = value.data;
+                    if !data.is_empty() && data.len() % 4 == 0 {
+                        len.fixed32_viable += 1;
+                        len.fixed32.extend(
+                            data.chunks_exact(4)
+                                .map(|b| i32::from_le_bytes(b.try_into().unwrap())),
+                        );
+                    }
+                    if !data.is_empty() && data.len() % 8 == 0 {
+                        len.fixed64_viable += 1;
+                        len.fixed64.extend(
+                            data.chunks_exact(8)
+                                .map(|b| i64::from_le_bytes(b.try_into().unwrap())),
+                        );
+                    }
+                    for (i, candidate) in value.candidates.iter().enumerate() {
+                        let best = i == 0;
+                        match &candidate.interpretation {
+                            Interpretation::Message(fields) => {
+                                len.message_viable += 1;
+                                len.message_best += best as usize;
+                                len.nested.add(fields);
+                            }
+                            Interpretation::String(_) => {
+                                len.string_viable += 1;
+                                len.string_best += best as usize;
+                            }
+                            Interpretation::Packed(v) => {
+                                len.packed_viable += 1;
+                                len.packed_best += best as usize;
+                                len.packed.extend_from_slice(v);
+                            }
+                            Interpretation::Bytes => {}
+                        }
+                    }
+                }
+            }
+        }
+        for (number, count) in counts {
+            let stats = self.fields.get_mut(&number).unwrap();
+            stats.max_count = stats.max_count.max(count);
+        }
+    }
+
+    fn to_descriptor(&self, name: &str, full_name: &str) -> MessageDescriptor {
+        let mut msg = MessageDescriptor {
+            name: name.to_string(),
+            full_name: full_name.to_string(),
+            ..Default::default()
+        };
+        for (&number, stats) in &self.fields {
+            let mut repeated = stats.max_count > 1;
+            let mut packed = None;
+            let field_type = match stats.wire_kind() {
+                kind @ (WireKind::Varint | WireKind::Fix32 | WireKind::Fix64) => {
+                    // proto3 packs repeated scalars unless told otherwise
+                    if repeated && stats.len.count == 0 {
+                        packed = Some(false);
+                    }
+                    match kind {
+                        WireKind::Varint => varint_type(&stats.varints),
+                        WireKind::Fix32 => fix32_type(&stats.fix32),
+                        _ => fix64_type(&stats.fix64),
+                    }
+                }
+                WireKind::Len => match stats.len.kind() {
+                    LenKind::Message => {
+                        let nested_name = format!("Field{}", number);
+                        let nested_full = format!("{}.{}", full_name, nested_name);
+                        msg.messages
+                            .push(stats.len.nested.to_descriptor(&nested_name, &nested_full));
+                        FieldType::Message(nested_full)
+                    }
+                    LenKind::String => FieldType::String,
+                    LenKind::Packed => {
+                        repeated = true;
+                        let mut values = stats.len.packed.clone();
+                        values.extend_from_slice(&stats.varints);
+                        varint_type(&values)
+                    }
+                    LenKind::Fixed32 => {
+                        repeated = true;
+                        let mut values = stats.len.fixed32.clone();
+                        values.extend_from_slice(&stats.fix32);
+                        fix32_type(&values)
+                    }
+                    LenKind::Fixed64 => {
+                        repeated = true;
+                        let mut values = stats.len.fixed64.clone();
+                        values.extend_from_slice(&stats.fix64);
+                        fix64_type(&values)
+                    }
+                    LenKind::Bytes => FieldType::Bytes,
+                },
+            };
+            msg.fields.push(FieldDescriptor {
+                name: format!("field_{}", number),
+                number,
+                label: if repeated {
+                    Label::Repeated
+                } else {
+                    Label::Singular
+                },
+                field_type,
+                oneof: None,
+                packed,
+            });
+        }
+        msg
+    }
+}
+
+enum WireKind {
+    Varint,
+    Fix32,
+    Fix64,
+    Len,
+}
+
+impl FieldStats {
+    /// the wire type seen most often, scalars written unpacked in some samples and packed in
+    /// others count as packed
+    fn wire_kind(&self) -> WireKind {
+        let len = self.len.count;
+        if self.len.packed_viable == len && len > 0 && !self.varints.is_empty() {
+            return WireKind::Len;
+        }
+        if len > 0 && (!self.fix32.is_empty() || !self.fix64.is_empty()) {
+            match self.len.kind() {
+                LenKind::Fixed32 if !self.fix32.is_empty() => return WireKind::Len,
+                LenKind::Fixed64 if !self.fix64.is_empty() => return WireKind::Len,
+                _ => {}
+            }
+        }
+        // equal counts go to the higher priority, varints first
+        let counts = [
+            (self.varints.len(), 3, WireKind::Varint),
+            (len, 2, WireKind::Len),
+            (self.fix32.len(), 1, WireKind::Fix32),
+            (self.fix64.len(), 0, WireKind::Fix64),
+        ];
+        counts
+            .into_iter()
+            .max_by_key(|(count, priority, _)| (*count, *priority))
+            .map(|(_, _, kind)| kind)
+            .unwrap_or(WireKind::Len)
+    }
+}
+
+enum LenKind {
+    Message,
+    String,
+    Packed,
+    Fixed32,
+    Fixed64,
+    Bytes,
+}
+
+impl LenStats {
+    /// the interpretation which fits every occurrence and is the best guess most often, packed
+    /// varints and bytes may turn out to be packed fixed-width values
+    fn kind(&self) -> LenKind {
+        match self.message_kind() {
+            LenKind::Packed => self.fixed_kind(true).unwrap_or(LenKind::Packed),
+            LenKind::Bytes => self.fixed_kind(false).unwrap_or(LenKind::Bytes),
+            kind => kind,
+        }
+    }
+
+    /// packed floats and doubles by their plausible values. packed fixed integers also read
+    /// as packed varints, their high zero bytes become zero varints, so they need a zero
+    /// varint per value and values which leave the top byte empty
+    fn fixed_kind(&self, packed: bool) -> Option<LenKind> {
+        let fixed32 = self.fixed32_viable == self.count;
+        let fixed64 = self.fixed64_viable == self.count;
+        if fixed64 && fix64_type(&self.fixed64) == FieldType::Double {
+            return Some(LenKind::Fixed64);
+        }
+        if fixed32 && fix32_type(&self.fixed32) == FieldType::Float {
+            return Some(LenKind::Fixed32);
+        }
+        let zeros = self.packed.iter().filter(|&&v| v == 0).count();
+        if !packed || !fixed32 || zeros < self.fixed32.len() {
+            return None;
+        }
+        if fixed64 && self.fixed64.iter().all(|&v| i32::try_from(v).is_ok()) {
+            return Some(LenKind::Fixed64);
+        }
+        let small = |v: i32| (-1 << 24..1 << 24).contains(&v);
+        self.fixed32
+            .iter()
+            .all(|&v| small(v))
+            .then_some(LenKind::Fixed32)
+    }
+
+    /// the interpretation of [`crate::interpret`] which fits every occurrence and is the best
+    /// guess most often
+    fn message_kind(&self) -> LenKind {
+        // equal counts go to the higher priority, a message before a string before packed
+        let candidates = [
+            (self.message_viable, self.message_best, 2, LenKind::Message),
+            (self.string_viable, self.string_best, 1, LenKind::String),
+            (self.packed_viable, self.packed_best, 0, LenKind::Packed),
+        ];
+        candidates
+            .into_iter()
+            .filter(|(viable, best, _, _)| *viable == self.count && *best > 0)
+            .max_by_key(|(_, best, priority, _)| (*best, *priority))
+            .map(|(_, _, _, kind)| kind)
+            .unwrap_or(LenKind::Bytes)
+    }
+}
+
+/// `bool` for 0 and 1 only, `int32`/`int64` for two's complement negatives, `sint32`/`sint64`
+/// if the values look zigzag encoded, otherwise `int32` or `int64` by size
+fn varint_type(values: &[u64]) -> FieldType {
+    if values.iter().all(|&v| v <= 1) {
+        return FieldType::Bool;
+    }
+    let fits_i32 = |v: u64| i32::try_from(v as i64).is_ok();
+    if values.iter().any(|&v| v >= 1 << 63) {
+        return match values.iter().all(|&v| fits_i32(v)) {
+            true => FieldType::Int32,
+            false => FieldType::Int64,
+        };
+    }
+    if looks_zigzag(values) {
+        return match values.iter().all(|&v| v <= u32::MAX as u64) {
+            true => FieldType::Sint32,
+            false => FieldType::Sint64,
+        };
+    }
+    match values.iter().all(|&v| fits_i32(v)) {
+        true => FieldType::Int32,
+        false => FieldType::Int64,
+    }
+}
+
+/// zigzag encoding maps negatives to odd numbers, so values which are mostly odd are mostly
+/// negative numbers. plain non-negative ints have both parities about as often, a range like
+/// `0..100` could be zigzag encoded `-50..50` as well but is kept as `int`. odd ids or flags
+/// with a few large values among small ones are kept as `int` too, since the decoded
+/// magnitudes of signed values stay close to each other
+fn looks_zigzag(values: &[u64]) -> bool {
+    let odd = values.iter().filter(|&&v| v & 1 == 1).count();
+    if values.len() < 8 || odd * 4 < values.len() * 3 {
+        return false;
+    }
+    let mut magnitudes: Vec<_> = values.iter().map(|&v| (v >> 1) + (v & 1)).collect();
+    magnitudes.sort_unstable();
+    let median = magnitudes[magnitudes.len() / 2].max(1);
+    magnitudes[magnitudes.len() - 1] <= median.saturating_mul(8)
+}
+
+/// floats have a sign, exponent and mantissa, integers stored as floats mostly have an
+/// exponent close to zero or are tiny denormals
+fn plausible_float(v: f64) -> bool {
+    v == 0.0 || (v.is_finite() && (1e-9..1e12).contains(&v.abs()))
+}
+
+fn fix32_type(values: &[i32]) -> FieldType {
+    let floats = values
+        .iter()
+        .filter(|&&v| plausible_float(f32::from_bits(v as u32) as f64))
+        .count();
+    match () {
+        _ if floats * 10 >= values.len() * 9 && values.iter().any(|&v| v != 0) => FieldType::Float,
+        _ if values.iter().any(|&v| v < 0) => FieldType::Sfixed32,
+        _ => FieldType::Fixed32,
+    }
+}
+
+fn fix64_type(values: &[i64]) -> FieldType {
+    let floats = values
+        .iter()
+        .filter(|&&v| plausible_float(f64::from_bits(v as u64)))
+        .count();
+    match () {
+        _ if floats * 10 >= values.len() * 9 && values.iter().any(|&v| v != 0) => FieldType::Double,
+        _ if values.iter().any(|&v| v < 0) => FieldType::Sfixed64,
+        _ => FieldType::Fixed64,
+    }
+}
+
+/// infer a message type from encoded samples of it
+///
+/// the samples are decoded with every interpretation of their length-delimited values
+/// (see [`crate::interpret`]) and merged per field. nested messages become nested types named
+/// `Field<number>`, fields are named `field_<number>`. a field is repeated if it occurs more
+/// than once in a sample or is packed. the result is proto3, so packed fields need no option
+/// and repeated scalars which only occur unpacked get `[packed = false]`.
+///
+/// example
+/// ```
+/// use protobuf_lite::protobuf::{Map, ProtoData};
+/// use protobuf_lite::schema::infer_schema;
+/// fn main() {
+///     let samples = (0..10u64).map(|i| {
+///         let mut pb = Map::new();
+///         pb.insert(1, ProtoData::Varint(1000 + i));
+///         pb.insert(2, format!("user {}", i).into());
+///         pb.insert(3, (i as f64 * 0.5 + 0.25).into());
+///         pb.encode().unwrap()
+///     });
+///     let file = infer_schema(samples, "demo", "User").unwrap();
+///     assert_eq!(
+///         file.to_proto(),
+///         "syntax = \"proto3\";\n\npackage demo;\n\nmessage User {\n  int32 field_1 = 1;\n  string field_2 = 2;\n  double field_3 = 3;\n}\n"
+///     );
+/// }
+/// ```
+pub fn infer_schema<I, T>(
+    samples: I,
+    package: &str,
+    name: &str,
+) -> Result<FileDescriptor, DecodeError>
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<[u8]>,
+{
+    let mut stats = MessageStats::default();
+    for sample in samples {
+        stats.add(&decode_annotated(sample.as_ref())?);
+    }
+    let full_name = match package {
+        "" => name.to_string(),
+        package => format!("{}.{}", package, name),
+    };
+    Ok(FileDescriptor {
+        name: format!("{}.proto", name.to_lowercase()),
+        syntax: Syntax::Proto3,
+        package: package.to_string(),
+        imports: Vec::new(),
+        messages: vec![stats.to_descriptor(name, &full_name)],
+        enums: Vec::new(),
+    })
+}
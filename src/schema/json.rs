@@ -0,0 +1,609 @@
+//! canonical proto3 JSON rendering of encoded messages described by a pool
+use super::{DescriptorPool, FieldDescriptor, FieldType, Label, MessageDescriptor};
+use crate::error::{DecodeError, SchemaError};
+use crate::event::{Field, Fields, Value};
+use crate::interpret::{self, AnnotatedField, AnnotatedValue};
+use crate::json::escape_string;
+use crate::protobuf::WireType;
+use crate::varint::decode_uvarint;
+use std::mem;
+use std::str;
+
+/// messages with their own JSON mapping instead of an object of their fields
+const WELL_KNOWN: &[&str] = &[
+    "google.protobuf.Any",
+    "google.protobuf.Duration",
+    "google.protobuf.FieldMask",
+    "google.protobuf.ListValue",
+    "google.protobuf.Struct",
+    "google.protobuf.Timestamp",
+    "google.protobuf.Value",
+    "google.protobuf.DoubleValue",
+    "google.protobuf.FloatValue",
+    "google.protobuf.Int64Value",
+    "google.protobuf.UInt64Value",
+    "google.protobuf.Int32Value",
+    "google.protobuf.UInt32Value",
+    "google.protobuf.BoolValue",
+    "google.protobuf.StringValue",
+    "google.protobuf.BytesValue",
+];
+
+impl DescriptorPool {
+    /// decode a message of the named type and render it as JSON
+    ///
+    /// follows the canonical proto3 JSON mapping: fields are keyed by their lowerCamelCase
+    /// name, 64-bit integers are strings, bytes are base64, enums are rendered by value name
+    /// and the well-known types like `Timestamp`, `Duration` or `Struct` by their own mapping.
+    /// `Any` is unpacked if its type is in the pool. fields missing from the schema are
+    /// rendered like [`crate::protobuf::ProtoData`]'s JSON output, keyed by number
+    ///
+    /// example
+    /// ```
+    /// use protobuf_lite::protobuf::{Map, ProtoData};
+    /// use protobuf_lite::schema::DescriptorPool;
+    /// fn main() {
+    ///     let mut pool = DescriptorPool::new();
+    ///     pool.add_source(
+    ///         "demo.proto",
+    ///         r#"
+    ///         syntax = "proto3";
+    ///         package demo;
+    ///         import "google/protobuf/timestamp.proto";
+    ///         message Event {
+    ///             Kind kind = 1;
+    ///             google.protobuf.Timestamp created_at = 2;
+    ///             int64 user_id = 3;
+    ///         }
+    ///         enum Kind { KIND_UNKNOWN = 0; KIND_LOGIN = 1; }
+    ///         "#,
+    ///     )
+    ///     .unwrap();
+    ///     let mut created = Map::new();
+    ///     created.insert(1, ProtoData::Varint(1_700_000_000));
+    ///     created.insert(2, ProtoData::Varint(500_000_000));
+    ///     let mut pb = Map::new();
+    ///     pb.insert(1, ProtoData::Varint(1));
+    ///     pb.insert(2, created.into());
+    ///     pb.insert(3, ProtoData::Varint(42));
+    ///     assert_eq!(
+    ///         pool.to_json("demo.Event", &pb.encode().unwrap()).unwrap(),
+    ///         r#"{"kind": "KIND_LOGIN", "createdAt": "2023-11-14T22:13:20.500Z", "userId": "42"}"#
+    ///     );
+    /// }
+    /// ```
+    pub fn to_json(&self, message: &str, data: &[u8]) -> Result<String, SchemaError> {
+        let mut writer = JsonWriter {
+            pool: self,
+            out: String::new(),
+        };
+        writer.message_named(message, data)?;
+        Ok(writer.out)
+    }
+}
+
+struct JsonWriter<'p> {
+    pool: &'p DescriptorPool,
+    out: String,
+}
+
+impl JsonWriter<'_> {
+    /// run `f` against an empty output and return what it wrote
+    fn render<F>(&mut self, f: F) -> Result<String, SchemaError>
+    where
+        F: FnOnce(&mut Self) -> Result<(), SchemaError>,
+    {
+        let outer = mem::take(&mut self.out);
+        let result = f(self);
+        let inner = mem::replace(&mut self.out, outer);
+        result.map(|_| inner)
+    }
+
+    fn key(&mut self, name: &str, first: &mut bool) {
+        if !mem::take(first) {
+            self.out.push_str(", ");
+        }
+        self.out.push('"');
+        self.out.push_str(&escape_string(name));
+        self.out.push_str("\": ");
+    }
+
+    fn message_named(&mut self, name: &str, data: &[u8]) -> Result<(), SchemaError> {
+        let desc = self
+            .pool
+            .message(name)
+            .ok_or_else(|| SchemaError::UnknownType(name.to_string()))?;
+        self.message(desc, data)
+    }
+
+    fn message(&mut self, desc: &MessageDescriptor, data: &[u8]) -> Result<(), SchemaError> {
+        let fields = Fields::new(data).collect::<Result<Vec<_>, _>>()?;
+        if WELL_KNOWN.contains(&desc.full_name.as_str()) {
+            return self.well_known(desc, &fields);
+        }
+        self.out.push('{');
+        self.members(desc, &fields, true)?;
+        self.out.push('}');
+        Ok(())
+    }
+
+    /// write the fields of a message without the enclosing braces
+    fn members(
+        &mut self,
+        desc: &MessageDescriptor,
+        fields: &[Field<'_>],
+        mut first: bool,
+    ) -> Result<(), SchemaError> {
+        for fd in &desc.fields {
+            let values: Vec<_> = fields
+                .iter()
+                .filter(|f| f.number == fd.number)
+                .map(|f| f.value)
+                .collect();
+            if values.is_empty() {
+                continue;
+            }
+            self.key(&json_name(&fd.name), &mut first);
+            self.field(fd, &values)?;
+        }
+        let unknown = fields
+            .iter()
+            .filter(|f| desc.field(f.number).is_none())
+            .map(annotate)
+            .collect::<Result<Vec<_>, _>>()?;
+        for (number, value) in interpret::to_map(&unknown).iter() {
+            self.key(&number.to_string(), &mut first);
+            self.out.push_str(&value.to_string());
+        }
+        Ok(())
+    }
+
+    /// write every occurrence of a field
+    fn field(&mut self, fd: &FieldDescriptor, values: &[Value<'_>]) -> Result<(), SchemaError> {
+        match (&fd.field_type, fd.label) {
+            (FieldType::Map(key, value), _) => self.map(fd.number, key, value, values),
+            (ty, Label::Repeated) => {
+                self.out.push('[');
+                let mut first = true;
+                for &v in values {
+                    for v in unpack(ty, v)? {
+                        if !mem::take(&mut first) {
+                            self.out.push_str(", ");
+                        }
+                        self.value(fd.number, ty, v)?;
+                    }
+                }
+                self.out.push(']');
+                Ok(())
+            }
+            // occurrences of a singular message are merged, which is the same as concatenating
+            (FieldType::Message(name), _) if values.len() > 1 => {
+                let mut data = Vec::new();
+                for v in values {
+                    match v {
+                        Value::Len(v) => data.extend_from_slice(v),
+                        v => return Err(unexpected_wire_type(fd.number, &fd.field_type, v)),
+                    }
+                }
+                self.message_named(name, &data)
+            }
+            // the last occurrence of a scalar wins
+            (ty, _) => self.value(fd.number, ty, values[values.len() - 1]),
+        }
+    }
+
+    fn value(&mut self, number: u64, ty: &FieldType, v: Value<'_>) -> Result<(), SchemaError> {
+        let text = match (ty, v) {
+            (FieldType::Double, Value::Fix64(v)) => {
+                let v = f64::from_bits(v as u64);
+                float(v, v.to_string())
+            }
+            (FieldType::Float, Value::Fix32(v)) => {
+                let v = f32::from_bits(v as u32);
+                float(v as f64, v.to_string())
+            }
+            (FieldType::Int64, Value::Varint(v)) => format!("\"{}\"", v as i64),
+            (FieldType::Uint64, Value::Varint(v)) => format!("\"{}\"", v),
+            (FieldType::Int32, Value::Varint(v)) => (v as i32).to_string(),
+            (FieldType::Uint32, Value::Varint(v)) => (v as u32).to_string(),
+            (FieldType::Sint64, v @ Value::Varint(_)) => format!("\"{}\"", v.as_sint64().unwrap()),
+            (FieldType::Sint32, v @ Value::Varint(_)) => {
+                (v.as_sint64().unwrap() as i32).to_string()
+            }
+            (FieldType::Fixed64, Value::Fix64(v)) => format!("\"{}\"", v as u64),
+            (FieldType::Sfixed64, Value::Fix64(v)) => format!("\"{}\"", v),
+            (FieldType::Fixed32, Value::Fix32(v)) => (v as u32).to_string(),
+            (FieldType::Sfixed32, Value::Fix32(v)) => v.to_string(),
+            (FieldType::Bool, Value::Varint(v)) => (v != 0).to_string(),
+            (FieldType::String, Value::Len(v)) => {
+                let v = str::from_utf8(v).map_err(DecodeError::from)?;
+                format!("\"{}\"", escape_string(v))
+            }
+            (FieldType::Bytes, Value::Len(v)) => format!("\"{}\"", base64(v)),
+            (FieldType::Enum(name), Value::Varint(v)) => self.enum_value(name, v as i32),
+            (FieldType::Message(name), Value::Len(v)) => return self.message_named(name, v),
+            (ty, v) => return Err(unexpected_wire_type(number, ty, &v)),
+        };
+        self.out.push_str(&text);
+        Ok(())
+    }
+
+    /// the default of a type, written for fields missing from map entries and wrappers
+    fn default_value(&mut self, number: u64, ty: &FieldType) -> Result<(), SchemaError> {
+        let v = match wire_type(ty) {
+            WireType::I64 => Value::Fix64(0),
+            WireType::I32 => Value::Fix32(0),
+            WireType::LEN => Value::Len(&[]),
+            _ => Value::Varint(0),
+        };
+        self.value(number, ty, v)
+    }
+
+    fn enum_value(&self, name: &str, v: i32) -> String {
+        if name == "google.protobuf.NullValue" {
+            return "null".to_string();
+        }
+        match self.pool.enumeration(name).and_then(|e| e.value_name(v)) {
+            Some(name) => format!("\"{}\"", escape_string(name)),
+            None => v.to_string(),
+        }
+    }
+
+    /// entries are written as an object keyed by the rendered key, later entries replace
+    /// earlier ones with the same key
+    fn map(
+        &mut self,
+        number: u64,
+        key: &FieldType,
+        value: &FieldType,
+        entries: &[Value<'_>],
+    ) -> Result<(), SchemaError> {
+        let mut rendered: Vec<(String, String)> = Vec::new();
+        for entry in entries {
+            let Value::Len(entry) = *entry else {
+                return Err(unexpected_wire_type(number, value, entry));
+            };
+            let fields = Fields::new(entry).collect::<Result<Vec<_>, _>>()?;
+            let last = |n| fields.iter().rev().find(|f| f.number == n).map(|f| f.value);
+            let k = self.render(|w| match last(1) {
+                Some(v) => w.value(1, key, v),
+                None => w.default_value(1, key),
+            })?;
+            let k = match k.starts_with('"') {
+                true => k,
+                false => format!("\"{}\"", k),
+            };
+            let v = self.render(|w| match last(2) {
+                Some(v) => w.value(2, value, v),
+                None => w.default_value(2, value),
+            })?;
+            match rendered.iter_mut().find(|(existing, _)| *existing == k) {
+                Some(existing) => existing.1 = v,
+                None => rendered.push((k, v)),
+            }
+        }
+        self.out.push('{');
+        for (i, (k, v)) in rendered.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            self.out.push_str(k);
+            self.out.push_str(": ");
+            self.out.push_str(v);
+        }
+        self.out.push('}');
+        Ok(())
+    }
+
+    fn well_known(
+        &mut self,
+        desc: &MessageDescriptor,
+        fields: &[Field<'_>],
+    ) -> Result<(), SchemaError> {
+        let last = |n| fields.iter().rev().find(|f| f.number == n).map(|f| f.value);
+        match desc.full_name.trim_start_matches("google.protobuf.") {
+            "Any" => return self.any(fields),
+            "Timestamp" => {
+                let (seconds, nanos) = seconds_nanos(fields)?;
+                if !(MIN_TIMESTAMP..=MAX_TIMESTAMP).contains(&seconds) {
+                    return Err(DecodeError::InvalidFieldValue(1).into());
+                }
+                if !(0..1_000_000_000).contains(&nanos) {
+                    return Err(DecodeError::InvalidFieldValue(2).into());
+                }
+                self.out
+                    .push_str(&format!("\"{}\"", timestamp(seconds, nanos)));
+            }
+            "Duration" => {
+                let (seconds, nanos) = seconds_nanos(fields)?;
+                if seconds.unsigned_abs() > MAX_DURATION {
+                    return Err(DecodeError::InvalidFieldValue(1).into());
+                }
+                // a negative duration has both parts negative
+                let mixed = (seconds < 0 && nanos > 0) || (seconds > 0 && nanos < 0);
+                if nanos.unsigned_abs() >= 1_000_000_000 || mixed {
+                    return Err(DecodeError::InvalidFieldValue(2).into());
+                }
+                let sign = if seconds < 0 || nanos < 0 { "-" } else { "" };
+                let fraction = fraction(nanos.unsigned_abs());
+                let seconds = seconds.unsigned_abs();
+                self.out
+                    .push_str(&format!("\"{}{}{}s\"", sign, seconds, fraction));
+            }
+            "FieldMask" => {
+                let mut paths = Vec::new();
+                for f in fields.iter().filter(|f| f.number == 1) {
+                    let path = f
+                        .value
+                        .as_bytes()
+                        .ok_or_else(|| unexpected_wire_type(1, &FieldType::String, &f.value))?;
+                    paths.push(json_name(str::from_utf8(path).map_err(DecodeError::from)?));
+                }
+                self.out
+                    .push_str(&format!("\"{}\"", escape_string(&paths.join(","))));
+            }
+            "Struct" | "ListValue" => {
+                let values: Vec<_> = fields
+                    .iter()
+                    .filter(|f| f.number == 1)
+                    .map(|f| f.value)
+                    .collect();
+                match desc.field(1) {
+                    Some(fd) if !values.is_empty() => self.field(fd, &values)?,
+                    _ if desc.name == "Struct" => self.out.push_str("{}"),
+                    _ => self.out.push_str("[]"),
+                }
+            }
+            // a oneof, the last field set wins
+            "Value" => {
+                let set = fields
+                    .iter()
+                    .rev()
+                    .find_map(|f| Some((f, desc.field(f.number)?)));
+                match set {
+                    Some((f, fd)) => self.value(f.number, &fd.field_type, f.value)?,
+                    None => self.out.push_str("null"),
+                }
+            }
+            // wrappers are written as their bare value
+            _ => match (desc.field(1), last(1)) {
+                (Some(fd), Some(v)) => self.value(1, &fd.field_type, v)?,
+                (Some(fd), None) => self.default_value(1, &fd.field_type)?,
+                (None, _) => self.out.push_str("null"),
+            },
+        }
+        Ok(())
+    }
+
+    /// `{"@type": url, ...fields}`, well-known types and messages of unknown types put their
+    /// value into a `value` member instead
+    fn any(&mut self, fields: &[Field<'_>]) -> Result<(), SchemaError> {
+        let last = |n| fields.iter().rev().find(|f| f.number == n).map(|f| f.value);
+        let type_url = match last(1) {
+            Some(v @ Value::Len(_)) => v.as_str().ok_or(DecodeError::InvalidFieldValue(1))?,
+            Some(v) => return Err(unexpected_wire_type(1, &FieldType::String, &v)),
+            None => "",
+        };
+        let value = match last(2) {
+            Some(Value::Len(v)) => v,
+            Some(v) => return Err(unexpected_wire_type(2, &FieldType::Bytes, &v)),
+            None => &[],
+        };
+        if type_url.is_empty() && value.is_empty() {
+            self.out.push_str("{}");
+            return Ok(());
+        }
+        self.out
+            .push_str(&format!("{{\"@type\": \"{}\"", escape_string(type_url)));
+        let name = type_url.rsplit('/').next().unwrap_or_default();
+        match self.pool.message(name) {
+            Some(desc) if WELL_KNOWN.contains(&desc.full_name.as_str()) => {
+                self.out.push_str(", \"value\": ");
+                self.message(desc, value)?;
+            }
+            Some(desc) => {
+                let fields = Fields::new(value).collect::<Result<Vec<_>, _>>()?;
+                self.members(desc, &fields, false)?;
+            }
+            None => {
+                self.out
+                    .push_str(&format!(", \"value\": \"{}\"", base64(value)));
+            }
+        }
+        self.out.push('}');
+        Ok(())
+    }
+}
+
+/// lowerCamelCase name of a field, like protoc derives it
+fn json_name(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.chars() {
+        match c {
+            '_' => upper = true,
+            c if upper => {
+                result.push(c.to_ascii_uppercase());
+                upper = false;
+            }
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+fn wire_type(ty: &FieldType) -> WireType {
+    match ty {
+        FieldType::Double | FieldType::Fixed64 | FieldType::Sfixed64 => WireType::I64,
+        FieldType::Float | FieldType::Fixed32 | FieldType::Sfixed32 => WireType::I32,
+        FieldType::String
+        | FieldType::Bytes
+        | FieldType::Message(_)
+        | FieldType::Named(_)
+        | FieldType::Map(..) => WireType::LEN,
+        _ => WireType::VARINT,
+    }
+}
+
+fn unexpected_wire_type(field: u64, ty: &FieldType, v: &Value<'_>) -> SchemaError {
+    #[allow(deprecated)]
+    let actual = match v {
+        Value::Varint(_) => WireType::VARINT,
+        Value::Fix64(_) => WireType::I64,
+        Value::Fix32(_) => WireType::I32,
+        Value::Len(_) => WireType::LEN,
+        Value::StartGroup => WireType::SGROUP,
+        Value::EndGroup => WireType::EGROUP,
+    };
+    DecodeError::UnexpectedWireType {
+        field,
+        expected: wire_type(ty),
+        actual,
+    }
+    .into()
+}
+
+/// split a packed value of a repeated scalar into its elements, other values are passed on
+fn unpack<'a>(ty: &FieldType, v: Value<'a>) -> Result<Vec<Value<'a>>, DecodeError> {
+    let Value::Len(mut data) = v else {
+        return Ok(vec![v]);
+    };
+    let values = match wire_type(ty) {
+        WireType::VARINT => {
+            let mut values = Vec::new();
+            while !data.is_empty() {
+                let (v, n) = decode_uvarint(data)?;
+                values.push(Value::Varint(v));
+                data = &data[n..];
+            }
+            values
+        }
+        WireType::I64 => {
+            let chunks = data.chunks_exact(8);
+            if !chunks.remainder().is_empty() {
+                return Err(DecodeError::UnexpectedEof);
+            }
+            chunks
+                .map(|c| Value::Fix64(i64::from_le_bytes(c.try_into().unwrap())))
+                .collect()
+        }
+        WireType::I32 => {
+            let chunks = data.chunks_exact(4);
+            if !chunks.remainder().is_empty() {
+                return Err(DecodeError::UnexpectedEof);
+            }
+            chunks
+                .map(|c| Value::Fix32(i32::from_le_bytes(c.try_into().unwrap())))
+                .collect()
+        }
+        _ => vec![v],
+    };
+    Ok(values)
+}
+
+fn annotate<'a>(f: &Field<'a>) -> Result<AnnotatedField<'a>, DecodeError> {
+    let value = match f.value {
+        Value::Varint(v) => AnnotatedValue::Varint(v),
+        Value::Fix64(v) => AnnotatedValue::Fix64(v),
+        Value::Fix32(v) => AnnotatedValue::Fix32(v),
+        Value::Len(v) => AnnotatedValue::Len(interpret::interpret(v, f.value_offset)),
+        Value::StartGroup | Value::EndGroup => {
+            return Err(DecodeError::DeprecatedWireType(f.wire_type))
+        }
+    };
+    Ok(AnnotatedField {
+        number: f.number,
+        offset: f.offset,
+        value,
+    })
+}
+
+/// `seconds` and `nanos` of a `Timestamp` or `Duration`
+/// seconds of 0001-01-01T00:00:00Z and 9999-12-31T23:59:59Z, the range of a `Timestamp`
+const MIN_TIMESTAMP: i64 = -62_135_596_800;
+const MAX_TIMESTAMP: i64 = 253_402_300_799;
+
+/// seconds of 10000 years, the largest magnitude of a `Duration`
+const MAX_DURATION: u64 = 315_576_000_000;
+
+fn seconds_nanos(fields: &[Field<'_>]) -> Result<(i64, i32), SchemaError> {
+    let mut seconds = 0;
+    let mut nanos = 0;
+    for f in fields {
+        match (f.number, f.value) {
+            (1, Value::Varint(v)) => seconds = v as i64,
+            (2, Value::Varint(v)) => nanos = v as i32,
+            (1, v) => return Err(unexpected_wire_type(1, &FieldType::Int64, &v)),
+            (2, v) => return Err(unexpected_wire_type(2, &FieldType::Int32, &v)),
+            _ => {}
+        }
+    }
+    Ok((seconds, nanos))
+}
+
+/// nanoseconds as 0, 3, 6 or 9 fractional digits
+fn fraction(nanos: u32) -> String {
+    match nanos {
+        0 => String::new(),
+        n if n % 1_000_000 == 0 => format!(".{:03}", n / 1_000_000),
+        n if n % 1_000 == 0 => format!(".{:06}", n / 1_000),
+        n => format!(".{:09}", n),
+    }
+}
+
+/// RFC 3339 in UTC, the date is computed with the days-to-civil algorithm of the proleptic
+/// gregorian calendar
+fn timestamp(seconds: i64, nanos: i32) -> String {
+    let days = seconds.div_euclid(86_400);
+    let secs = seconds.rem_euclid(86_400);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}Z",
+        year,
+        month,
+        day,
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60,
+        fraction(nanos as u32)
+    )
+}
+
+/// NaN and infinities are strings in JSON
+fn float(v: f64, text: String) -> String {
+    match v {
+        _ if v.is_nan() => "\"NaN\"".to_string(),
+        f64::INFINITY => "\"Infinity\"".to_string(),
+        f64::NEG_INFINITY => "\"-Infinity\"".to_string(),
+        _ => text,
+    }
+}
+
+/// standard base64 with padding
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut result = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => result.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char),
+                false => result.push('='),
+            }
+        }
+    }
+    result
+}
@@ -0,0 +1,462 @@
+//! `.proto` schema descriptors
+//!
+//! example
+//! ```
+//! use protobuf_lite::schema::{DescriptorPool, FieldType};
+//! fn main() {
+//!     let mut pool = DescriptorPool::new();
+//!     pool.add_source(
+//!         "demo.proto",
+//!         r#"
+//!         syntax = "proto3";
+//!         package demo;
+//!         message User {
+//!             string name = 1;
+//!             Role role = 2;
+//!         }
+//!         enum Role { ROLE_UNKNOWN = 0; ROLE_ADMIN = 1; }
+//!         "#,
+//!     )
+//!     .unwrap();
+//!     let user = pool.message("demo.User").unwrap();
+//!     assert_eq!(user.fields[1].field_type, FieldType::Enum("demo.Role".to_string()));
+//! }
+//! ```
+use crate::error::SchemaError;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+mod infer;
+mod json;
+mod parser;
+mod wkt;
+
+pub use infer::infer_schema;
+pub use parser::parse;
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum Syntax {
+    #[default]
+    Proto2,
+    Proto3,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileDescriptor {
+    pub name: String,
+    pub syntax: Syntax,
+    pub package: String,
+    pub imports: Vec<String>,
+    pub messages: Vec<MessageDescriptor>,
+    pub enums: Vec<EnumDescriptor>,
+}
+
+impl FileDescriptor {
+    /// write the file as `.proto` source, type references are fully qualified
+    pub fn to_proto(&self) -> String {
+        let mut out = String::new();
+        let syntax = match self.syntax {
+            Syntax::Proto2 => "proto2",
+            Syntax::Proto3 => "proto3",
+        };
+        out.push_str(&format!("syntax = \"{}\";\n", syntax));
+        if !self.package.is_empty() {
+            out.push_str(&format!("\npackage {};\n", self.package));
+        }
+        if !self.imports.is_empty() {
+            out.push('\n');
+        }
+        for import in &self.imports {
+            out.push_str(&format!("import \"{}\";\n", import));
+        }
+        for msg in &self.messages {
+            out.push('\n');
+            write_message(&mut out, msg, 0);
+        }
+        for e in &self.enums {
+            out.push('\n');
+            write_enum(&mut out, e, 0);
+        }
+        out
+    }
+}
+
+fn write_message(out: &mut String, msg: &MessageDescriptor, depth: usize) {
+    let indent = "  ".repeat(depth);
+    out.push_str(&format!("{}message {} {{\n", indent, msg.name));
+    for nested in &msg.messages {
+        write_message(out, nested, depth + 1);
+    }
+    for e in &msg.enums {
+        write_enum(out, e, depth + 1);
+    }
+    let mut oneof = None;
+    for field in &msg.fields {
+        if field.oneof != oneof {
+            if oneof.is_some() {
+                out.push_str(&format!("{}  }}\n", indent));
+            }
+            if let Some(i) = field.oneof {
+                out.push_str(&format!("{}  oneof {} {{\n", indent, msg.oneofs[i]));
+            }
+            oneof = field.oneof;
+        }
+        let label = match field.label {
+            _ if field.oneof.is_some() => "",
+            Label::Singular => "",
+            Label::Optional => "optional ",
+            Label::Required => "required ",
+            Label::Repeated if matches!(field.field_type, FieldType::Map(..)) => "",
+            Label::Repeated => "repeated ",
+        };
+        let options = match field.packed {
+            Some(packed) => format!(" [packed = {}]", packed),
+            None => String::new(),
+        };
+        let extra = if field.oneof.is_some() { "  " } else { "" };
+        out.push_str(&format!(
+            "{}  {}{}{} {} = {}{};\n",
+            indent,
+            extra,
+            label,
+            field.field_type.proto_name(),
+            field.name,
+            field.number,
+            options
+        ));
+    }
+    if oneof.is_some() {
+        out.push_str(&format!("{}  }}\n", indent));
+    }
+    out.push_str(&format!("{}}}\n", indent));
+}
+
+fn write_enum(out: &mut String, e: &EnumDescriptor, depth: usize) {
+    let indent = "  ".repeat(depth);
+    out.push_str(&format!("{}enum {} {{\n", indent, e.name));
+    for (name, value) in &e.values {
+        out.push_str(&format!("{}  {} = {};\n", indent, name, value));
+    }
+    out.push_str(&format!("{}}}\n", indent));
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MessageDescriptor {
+    pub name: String,
+    /// fully qualified name without leading dot, e.g. `pkg.Outer.Inner`
+    pub full_name: String,
+    pub fields: Vec<FieldDescriptor>,
+    pub oneofs: Vec<String>,
+    pub messages: Vec<MessageDescriptor>,
+    pub enums: Vec<EnumDescriptor>,
+}
+
+impl MessageDescriptor {
+    /// gets a field by number
+    pub fn field(&self, number: u64) -> Option<&FieldDescriptor> {
+        self.fields.iter().find(|f| f.number == number)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Label {
+    /// proto3 field without label
+    Singular,
+    Optional,
+    Required,
+    Repeated,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDescriptor {
+    pub name: String,
+    pub number: u64,
+    pub label: Label,
+    pub field_type: FieldType,
+    /// index into [`MessageDescriptor::oneofs`]
+    pub oneof: Option<usize>,
+    /// explicit `[packed = ...]` option
+    pub packed: Option<bool>,
+}
+
+impl FieldDescriptor {
+    /// whether repeated scalars of this field are written packed
+    pub fn is_packed(&self, syntax: Syntax) -> bool {
+        self.label == Label::Repeated
+            && self.field_type.is_scalar_numeric()
+            && self.packed.unwrap_or(syntax == Syntax::Proto3)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum FieldType {
+    Double,
+    Float,
+    Int64,
+    Uint64,
+    Int32,
+    Fixed64,
+    Fixed32,
+    Bool,
+    String,
+    Bytes,
+    Uint32,
+    Sfixed32,
+    Sfixed64,
+    Sint32,
+    Sint64,
+    /// fully qualified message name
+    Message(String),
+    /// fully qualified enum name
+    Enum(String),
+    /// type reference before resolution
+    Named(String),
+    Map(Box<FieldType>, Box<FieldType>),
+}
+
+impl FieldType {
+    pub(crate) fn parse(name: &str) -> Self {
+        match name {
+            "double" => FieldType::Double,
+            "float" => FieldType::Float,
+            "int64" => FieldType::Int64,
+            "uint64" => FieldType::Uint64,
+            "int32" => FieldType::Int32,
+            "fixed64" => FieldType::Fixed64,
+            "fixed32" => FieldType::Fixed32,
+            "bool" => FieldType::Bool,
+            "string" => FieldType::String,
+            "bytes" => FieldType::Bytes,
+            "uint32" => FieldType::Uint32,
+            "sfixed32" => FieldType::Sfixed32,
+            "sfixed64" => FieldType::Sfixed64,
+            "sint32" => FieldType::Sint32,
+            "sint64" => FieldType::Sint64,
+            v => FieldType::Named(v.to_string()),
+        }
+    }
+
+    /// name of the type in `.proto` syntax
+    pub fn proto_name(&self) -> String {
+        match self {
+            FieldType::Double => "double".to_string(),
+            FieldType::Float => "float".to_string(),
+            FieldType::Int64 => "int64".to_string(),
+            FieldType::Uint64 => "uint64".to_string(),
+            FieldType::Int32 => "int32".to_string(),
+            FieldType::Fixed64 => "fixed64".to_string(),
+            FieldType::Fixed32 => "fixed32".to_string(),
+            FieldType::Bool => "bool".to_string(),
+            FieldType::String => "string".to_string(),
+            FieldType::Bytes => "bytes".to_string(),
+            FieldType::Uint32 => "uint32".to_string(),
+            FieldType::Sfixed32 => "sfixed32".to_string(),
+            FieldType::Sfixed64 => "sfixed64".to_string(),
+            FieldType::Sint32 => "sint32".to_string(),
+            FieldType::Sint64 => "sint64".to_string(),
+            FieldType::Message(v) | FieldType::Enum(v) => format!(".{}", v),
+            FieldType::Named(v) => v.clone(),
+            FieldType::Map(k, v) => format!("map<{}, {}>", k.proto_name(), v.proto_name()),
+        }
+    }
+
+    /// varint, 32-bit and 64-bit types, which may be packed
+    pub fn is_scalar_numeric(&self) -> bool {
+        !matches!(
+            self,
+            FieldType::String
+                | FieldType::Bytes
+                | FieldType::Message(_)
+                | FieldType::Named(_)
+                | FieldType::Map(..)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EnumDescriptor {
+    pub name: String,
+    /// fully qualified name without leading dot
+    pub full_name: String,
+    pub values: Vec<(String, i32)>,
+}
+
+impl EnumDescriptor {
+    /// gets the name of a value
+    pub fn value_name(&self, value: i32) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|(_, v)| *v == value)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+/// a set of resolved files
+#[derive(Debug, Clone, Default)]
+pub struct DescriptorPool {
+    files: Vec<FileDescriptor>,
+    messages: BTreeMap<String, MessageDescriptor>,
+    enums: BTreeMap<String, EnumDescriptor>,
+}
+
+impl DescriptorPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// gets all files in the order they were added, imports first
+    pub fn files(&self) -> &[FileDescriptor] {
+        &self.files
+    }
+
+    /// gets a file by the name it was added with
+    pub fn file(&self, name: &str) -> Option<&FileDescriptor> {
+        self.files.iter().find(|f| f.name == name)
+    }
+
+    /// gets a message by its fully qualified name, a leading dot is optional
+    pub fn message(&self, name: &str) -> Option<&MessageDescriptor> {
+        self.messages.get(name.trim_start_matches('.'))
+    }
+
+    /// gets an enum by its fully qualified name, a leading dot is optional
+    pub fn enumeration(&self, name: &str) -> Option<&EnumDescriptor> {
+        self.enums.get(name.trim_start_matches('.'))
+    }
+
+    /// gets all messages keyed by fully qualified name
+    pub fn messages(&self) -> impl Iterator<Item = &MessageDescriptor> {
+        self.messages.values()
+    }
+
+    /// parse and add a file, its imports must have been added before except for the
+    /// well-known types in `google/protobuf/`, which are built in
+    pub fn add_source(&mut self, name: &str, src: &str) -> Result<(), SchemaError> {
+        let mut file = parse(src)?;
+        file.name = name.to_string();
+        self.add_file(file)
+    }
+
+    /// add a parsed file and resolve its type references
+    pub fn add_file(&mut self, mut file: FileDescriptor) -> Result<(), SchemaError> {
+        if self.file(&file.name).is_some() {
+            return Ok(());
+        }
+        for import in &file.imports {
+            if let Some(src) = wkt::source(import) {
+                self.add_source(import, src)?;
+            }
+        }
+        for msg in &file.messages {
+            self.register_message(msg);
+        }
+        for e in &file.enums {
+            self.enums.insert(e.full_name.clone(), e.clone());
+        }
+        for msg in file.messages.iter_mut() {
+            self.resolve_message(msg)?;
+        }
+        for msg in &file.messages {
+            self.register_message(msg);
+        }
+        self.files.push(file);
+        Ok(())
+    }
+
+    /// read a file and its imports from the include directories
+    pub fn load<P>(&mut self, path: P, includes: &[PathBuf]) -> Result<(), SchemaError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let name = includes
+            .iter()
+            .find_map(|dir| path.strip_prefix(dir).ok())
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        if self.file(&name).is_some() {
+            return Ok(());
+        }
+        let src = fs::read_to_string(path)
+            .map_err(|err| SchemaError::Io(path.display().to_string(), err))?;
+        let mut file = parse(&src)?;
+        file.name = name;
+        for import in &file.imports {
+            let found = includes
+                .iter()
+                .map(|dir| dir.join(import))
+                .chain(path.parent().map(|dir| dir.join(import)))
+                .find(|p| p.exists());
+            match found {
+                Some(p) => self.load(p, includes)?,
+                None if import.starts_with("google/protobuf/") => {}
+                None => return Err(SchemaError::ImportNotFound(import.clone())),
+            }
+        }
+        self.add_file(file)
+    }
+
+    fn register_message(&mut self, msg: &MessageDescriptor) {
+        self.messages.insert(msg.full_name.clone(), msg.clone());
+        for nested in &msg.messages {
+            self.register_message(nested);
+        }
+        for e in &msg.enums {
+            self.enums.insert(e.full_name.clone(), e.clone());
+        }
+    }
+
+    fn resolve_message(&self, msg: &mut MessageDescriptor) -> Result<(), SchemaError> {
+        for field in msg.fields.iter_mut() {
+            field.field_type = self.resolve_type(&field.field_type, &msg.full_name)?;
+        }
+        for nested in msg.messages.iter_mut() {
+            self.resolve_message(nested)?;
+        }
+        Ok(())
+    }
+
+    fn resolve_type(&self, ty: &FieldType, scope: &str) -> Result<FieldType, SchemaError> {
+        match ty {
+            FieldType::Named(name) => self
+                .lookup(name, scope)
+                .ok_or_else(|| SchemaError::UnknownType(name.clone())),
+            FieldType::Map(k, v) => Ok(FieldType::Map(
+                Box::new(self.resolve_type(k, scope)?),
+                Box::new(self.resolve_type(v, scope)?),
+            )),
+            ty => Ok(ty.clone()),
+        }
+    }
+
+    /// resolve a type name with protobuf scoping rules, innermost scope first
+    fn lookup(&self, name: &str, scope: &str) -> Option<FieldType> {
+        let found = |full: &str| {
+            if self.messages.contains_key(full) {
+                Some(FieldType::Message(full.to_string()))
+            } else if self.enums.contains_key(full) {
+                Some(FieldType::Enum(full.to_string()))
+            } else {
+                None
+            }
+        };
+        if let Some(full) = name.strip_prefix('.') {
+            return found(full);
+        }
+        let mut scope = scope.to_string();
+        loop {
+            if let Some(ty) = found(&parser::join(&scope, name)) {
+                return Some(ty);
+            }
+            if scope.is_empty() {
+                return None;
+            }
+            scope = match scope.rfind('.') {
+                Some(i) => scope[..i].to_string(),
+                None => String::new(),
+            };
+        }
+    }
+}
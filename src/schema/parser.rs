@@ -0,0 +1,515 @@
+use crate::error::SchemaError;
+use crate::schema::{
+    EnumDescriptor, FieldDescriptor, FieldType, FileDescriptor, Label, MessageDescriptor, Syntax,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Int(String),
+    Float(String),
+    Str(String),
+    Symbol(char),
+}
+
+struct Lexer<'a> {
+    src: &'a [u8],
+    pos: usize,
+    line: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(src: &'a str) -> Self {
+        Lexer {
+            src: src.as_bytes(),
+            pos: 0,
+            line: 1,
+        }
+    }
+
+    fn error(&self, msg: impl Into<String>) -> SchemaError {
+        SchemaError::Parse {
+            line: self.line,
+            msg: msg.into(),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<u8> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == b'\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), SchemaError> {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_ascii_whitespace() => {
+                    self.bump();
+                }
+                Some(b'/') if self.src.get(self.pos + 1) == Some(&b'/') => {
+                    while !matches!(self.bump(), Some(b'\n') | None) {}
+                }
+                Some(b'/') if self.src.get(self.pos + 1) == Some(&b'*') => {
+                    self.pos += 2;
+                    loop {
+                        match self.bump() {
+                            Some(b'*') if self.peek() == Some(b'/') => {
+                                self.pos += 1;
+                                break;
+                            }
+                            Some(_) => {}
+                            None => return Err(self.error("unterminated comment")),
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn tokenize(mut self) -> Result<Vec<(Token, usize)>, SchemaError> {
+        let mut tokens = Vec::new();
+        loop {
+            self.skip_whitespace()?;
+            let line = self.line;
+            let Some(c) = self.peek() else {
+                return Ok(tokens);
+            };
+            let start = self.pos;
+            let token = if c.is_ascii_alphabetic() || c == b'_' {
+                while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == b'_' || c == b'.')
+                {
+                    self.bump();
+                }
+                Token::Ident(self.text(start))
+            } else if c.is_ascii_digit() || (c == b'-' && self.is_digit_at(self.pos + 1)) {
+                self.bump();
+                let mut float = false;
+                while let Some(c) = self.peek() {
+                    if c == b'.' || ((c == b'e' || c == b'E') && !self.is_hex(start)) {
+                        float = true;
+                    } else if (c == b'-' || c == b'+') && float {
+                    } else if !c.is_ascii_alphanumeric() {
+                        break;
+                    }
+                    self.bump();
+                }
+                match float {
+                    true => Token::Float(self.text(start)),
+                    false => Token::Int(self.text(start)),
+                }
+            } else if c == b'"' || c == b'\'' {
+                self.bump();
+                let mut value = Vec::new();
+                loop {
+                    match self.bump() {
+                        Some(b'\\') => match self.bump() {
+                            Some(b'n') => value.push(b'\n'),
+                            Some(b't') => value.push(b'\t'),
+                            Some(b'r') => value.push(b'\r'),
+                            Some(b'0') => value.push(0),
+                            Some(c) => value.push(c),
+                            None => return Err(self.error("unterminated string")),
+                        },
+                        Some(q) if q == c => break,
+                        Some(c) => value.push(c),
+                        None => return Err(self.error("unterminated string")),
+                    }
+                }
+                Token::Str(String::from_utf8_lossy(&value).into_owned())
+            } else {
+                self.bump();
+                Token::Symbol(c as char)
+            };
+            tokens.push((token, line));
+        }
+    }
+
+    fn text(&self, start: usize) -> String {
+        String::from_utf8_lossy(&self.src[start..self.pos]).into_owned()
+    }
+
+    fn is_digit_at(&self, pos: usize) -> bool {
+        matches!(self.src.get(pos), Some(c) if c.is_ascii_digit())
+    }
+
+    fn is_hex(&self, start: usize) -> bool {
+        let s = &self.src[start..self.pos];
+        s.starts_with(b"0x") || s.starts_with(b"0X") || s.starts_with(b"-0x")
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or(self.tokens.last())
+            .map(|(_, line)| *line)
+            .unwrap_or(1)
+    }
+
+    fn error(&self, msg: impl Into<String>) -> SchemaError {
+        SchemaError::Parse {
+            line: self.line(),
+            msg: msg.into(),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn next(&mut self) -> Result<Token, SchemaError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .map(|(t, _)| t.clone())
+            .ok_or_else(|| self.error("unexpected end of file"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn is_symbol(&self, c: char) -> bool {
+        self.peek() == Some(&Token::Symbol(c))
+    }
+
+    fn is_ident(&self, s: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(v)) if v == s)
+    }
+
+    fn eat_symbol(&mut self, c: char) -> bool {
+        if self.is_symbol(c) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect_symbol(&mut self, c: char) -> Result<(), SchemaError> {
+        if !self.eat_symbol(c) {
+            return Err(self.error(format!("expected `{}`", c)));
+        }
+        Ok(())
+    }
+
+    fn ident(&mut self) -> Result<String, SchemaError> {
+        match self.next()? {
+            Token::Ident(v) => Ok(v),
+            t => Err(self.error(format!("expected identifier, got {:?}", t))),
+        }
+    }
+
+    /// a type reference, fully qualified names start with `.`
+    fn type_name(&mut self) -> Result<String, SchemaError> {
+        if self.eat_symbol('.') {
+            return Ok(format!(".{}", self.ident()?));
+        }
+        self.ident()
+    }
+
+    fn string(&mut self) -> Result<String, SchemaError> {
+        let mut value = match self.next()? {
+            Token::Str(v) => v,
+            t => return Err(self.error(format!("expected string, got {:?}", t))),
+        };
+        // adjacent string literals are concatenated
+        while let Some(Token::Str(v)) = self.peek() {
+            value.push_str(v);
+            self.pos += 1;
+        }
+        Ok(value)
+    }
+
+    fn int(&mut self) -> Result<i64, SchemaError> {
+        let text = match self.next()? {
+            Token::Int(v) => v,
+            t => return Err(self.error(format!("expected integer, got {:?}", t))),
+        };
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(v) => (true, v),
+            None => (false, text.as_str()),
+        };
+        let value = if let Some(hex) = digits
+            .strip_prefix("0x")
+            .or_else(|| digits.strip_prefix("0X"))
+        {
+            i64::from_str_radix(hex, 16)
+        } else if digits.len() > 1 && digits.starts_with('0') {
+            i64::from_str_radix(&digits[1..], 8)
+        } else {
+            digits.parse()
+        }
+        .map_err(|_| self.error(format!("invalid integer `{}`", text)))?;
+        Ok(if negative { -value } else { value })
+    }
+
+    /// skip a balanced `{ ... }`, `[ ... ]` or `( ... )` block
+    fn skip_block(&mut self, open: char, close: char) -> Result<(), SchemaError> {
+        self.expect_symbol(open)?;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next()? {
+                Token::Symbol(c) if c == open => depth += 1,
+                Token::Symbol(c) if c == close => depth -= 1,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// skip everything until the next `;` outside of blocks
+    fn skip_statement(&mut self) -> Result<(), SchemaError> {
+        loop {
+            if self.is_symbol('{') {
+                self.skip_block('{', '}')?;
+                self.eat_symbol(';');
+                return Ok(());
+            }
+            if let Token::Symbol(';') = self.next()? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// parse `[packed = true, deprecated = true]` and return the `packed` option
+    fn field_options(&mut self) -> Result<Option<bool>, SchemaError> {
+        let mut packed = None;
+        if !self.eat_symbol('[') {
+            return Ok(packed);
+        }
+        loop {
+            let name = if self.is_symbol('(') {
+                self.skip_block('(', ')')?;
+                while !self.is_symbol('=') {
+                    self.next()?;
+                }
+                String::new()
+            } else {
+                self.ident()?
+            };
+            self.expect_symbol('=')?;
+            if self.is_symbol('{') {
+                self.skip_block('{', '}')?;
+            } else {
+                let value = self.next()?;
+                if name == "packed" {
+                    packed = Some(value == Token::Ident("true".to_string()));
+                }
+            }
+            if self.eat_symbol(']') {
+                return Ok(packed);
+            }
+            self.expect_symbol(',')?;
+        }
+    }
+
+    fn file(&mut self) -> Result<FileDescriptor, SchemaError> {
+        let mut file = FileDescriptor::default();
+        while self.peek().is_some() {
+            if self.eat_symbol(';') {
+                continue;
+            }
+            match self.ident()?.as_str() {
+                "syntax" | "edition" => {
+                    self.expect_symbol('=')?;
+                    file.syntax = match self.string()?.as_str() {
+                        "proto2" => Syntax::Proto2,
+                        "proto3" => Syntax::Proto3,
+                        v => return Err(self.error(format!("unsupported syntax `{}`", v))),
+                    };
+                    self.expect_symbol(';')?;
+                }
+                "package" => {
+                    file.package = self.ident()?;
+                    self.expect_symbol(';')?;
+                }
+                "import" => {
+                    if self.is_ident("public") || self.is_ident("weak") {
+                        self.next()?;
+                    }
+                    file.imports.push(self.string()?);
+                    self.expect_symbol(';')?;
+                }
+                "message" => {
+                    let msg = self.message(&file.package, file.syntax)?;
+                    file.messages.push(msg);
+                }
+                "enum" => {
+                    let e = self.enumeration(&file.package)?;
+                    file.enums.push(e);
+                }
+                "option" | "service" | "extend" => self.skip_statement()?,
+                v => return Err(self.error(format!("unexpected `{}`", v))),
+            }
+        }
+        Ok(file)
+    }
+
+    fn message(&mut self, scope: &str, syntax: Syntax) -> Result<MessageDescriptor, SchemaError> {
+        let name = self.ident()?;
+        let mut msg = MessageDescriptor {
+            full_name: join(scope, &name),
+            name,
+            ..Default::default()
+        };
+        self.expect_symbol('{')?;
+        self.message_body(&mut msg, syntax, None)?;
+        Ok(msg)
+    }
+
+    fn message_body(
+        &mut self,
+        msg: &mut MessageDescriptor,
+        syntax: Syntax,
+        oneof: Option<usize>,
+    ) -> Result<(), SchemaError> {
+        loop {
+            if self.eat_symbol('}') {
+                return Ok(());
+            }
+            if self.eat_symbol(';') {
+                continue;
+            }
+            let word = match self.peek() {
+                Some(Token::Ident(v)) => v.clone(),
+                // a field with a fully-qualified type like `.pkg.Msg`
+                Some(Token::Symbol('.')) => String::new(),
+                _ => return Err(self.error("expected field or definition")),
+            };
+            match word.as_str() {
+                "message" if oneof.is_none() => {
+                    self.next()?;
+                    let nested = self.message(&msg.full_name, syntax)?;
+                    msg.messages.push(nested);
+                }
+                "enum" if oneof.is_none() => {
+                    self.next()?;
+                    let e = self.enumeration(&msg.full_name)?;
+                    msg.enums.push(e);
+                }
+                "oneof" if oneof.is_none() => {
+                    self.next()?;
+                    msg.oneofs.push(self.ident()?);
+                    self.expect_symbol('{')?;
+                    self.message_body(msg, syntax, Some(msg.oneofs.len() - 1))?;
+                }
+                "option" | "reserved" | "extensions" | "extend" => {
+                    self.next()?;
+                    self.skip_statement()?;
+                }
+                _ => {
+                    let field = self.field(syntax, oneof)?;
+                    msg.fields.push(field);
+                }
+            }
+        }
+    }
+
+    fn field(
+        &mut self,
+        syntax: Syntax,
+        oneof: Option<usize>,
+    ) -> Result<FieldDescriptor, SchemaError> {
+        let mut label = match syntax {
+            Syntax::Proto2 => Label::Optional,
+            Syntax::Proto3 => Label::Singular,
+        };
+        for (word, l) in [
+            ("optional", Label::Optional),
+            ("required", Label::Required),
+            ("repeated", Label::Repeated),
+        ] {
+            if self.is_ident(word) {
+                self.next()?;
+                label = l;
+                break;
+            }
+        }
+        if oneof.is_some() {
+            label = Label::Optional;
+        }
+
+        let field_type = if self.is_ident("map") {
+            self.next()?;
+            self.expect_symbol('<')?;
+            let key = FieldType::parse(&self.type_name()?);
+            self.expect_symbol(',')?;
+            let value = FieldType::parse(&self.type_name()?);
+            self.expect_symbol('>')?;
+            label = Label::Repeated;
+            FieldType::Map(Box::new(key), Box::new(value))
+        } else {
+            FieldType::parse(&self.type_name()?)
+        };
+        let name = self.ident()?;
+        self.expect_symbol('=')?;
+        let number = self.int()?;
+        if !(1..=(1 << 29) - 1).contains(&number) {
+            return Err(self.error(format!("invalid field number {}", number)));
+        }
+        let packed = self.field_options()?;
+        self.expect_symbol(';')?;
+        Ok(FieldDescriptor {
+            name,
+            number: number as u64,
+            label,
+            field_type,
+            oneof,
+            packed,
+        })
+    }
+
+    fn enumeration(&mut self, scope: &str) -> Result<EnumDescriptor, SchemaError> {
+        let name = self.ident()?;
+        let mut e = EnumDescriptor {
+            full_name: join(scope, &name),
+            name,
+            values: Vec::new(),
+        };
+        self.expect_symbol('{')?;
+        loop {
+            if self.eat_symbol('}') {
+                return Ok(e);
+            }
+            if self.eat_symbol(';') {
+                continue;
+            }
+            let name = self.ident()?;
+            if name == "option" || name == "reserved" {
+                self.skip_statement()?;
+                continue;
+            }
+            self.expect_symbol('=')?;
+            let value = self.int()?;
+            self.field_options()?;
+            self.expect_symbol(';')?;
+            let value = i32::try_from(value).map_err(|_| self.error("enum value out of range"))?;
+            e.values.push((name, value));
+        }
+    }
+}
+
+pub(crate) fn join(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        return name.to_string();
+    }
+    format!("{}.{}", scope, name)
+}
+
+/// parse the source of a `.proto` file, type references are not resolved
+pub fn parse(src: &str) -> Result<FileDescriptor, SchemaError> {
+    let tokens = Lexer::new(src).tokenize()?;
+    Parser { tokens, pos: 0 }.file()
+}
@@ -0,0 +1,600 @@
+//! serialize rust structs to protobuf wire format
+//!
+//! a struct is a message, its fields are numbered by declaration order starting at 1.
+//! a field renamed to a number (`#[serde(rename = "5")]`) uses that number instead, field 0
+//! and numbers taken by two fields are rejected.
+//!
+//! | rust type                            | wire format                               |
+//! |--------------------------------------|-------------------------------------------|
+//! | `bool`, integers, `char`, unit enums | varint (signed integers as two's complement) |
+//! | `f32` / `f64`                        | 32-bit / 64-bit                           |
+//! | `String`, `&str`, bytes              | length-delimited                          |
+//! | struct                               | length-delimited message                  |
+//! | `Option<T>`                          | omitted when `None`                       |
+//! | `Vec<T>` and tuples                  | repeated field, not packed                |
+//! | maps                                 | repeated entry message `{1: key, 2: value}` |
+//! | newtype / struct enum variants       | message with the variant at field `index + 1` |
+//!
+//! use `serde_bytes` for `Vec<u8>` fields, otherwise they are serialized as repeated varint
+use crate::error::SerdeError;
+use crate::fixint::{write_double, write_float};
+use crate::protobuf::WireType;
+use crate::varint::write_uvarint;
+use serde::ser::{self, Impossible, Serialize};
+use std::io::Write;
+
+type Result<T> = std::result::Result<T, SerdeError>;
+
+/// serialize a struct to protobuf bytes
+///
+/// example
+/// ```
+/// use serde::Serialize;
+/// #[derive(Serialize)]
+/// struct Request {
+///     id: u64,
+///     #[serde(rename = "3")]
+///     name: String,
+/// }
+/// fn main() {
+///     let data = protobuf_lite::ser::to_bytes(&Request { id: 150, name: "a".into() }).unwrap();
+///     assert_eq!(data, vec![0x08, 0x96, 0x01, 0x1a, 0x01, b'a']);
+/// }
+/// ```
+pub fn to_bytes<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
+    let mut buf = Vec::new();
+    value.serialize(Serializer::new(&mut buf))?;
+    Ok(buf)
+}
+
+/// serialize a struct to protobuf bytes and write them to `writer`
+pub fn to_writer<W, T>(writer: &mut W, value: &T) -> Result<()>
+where
+    W: Write,
+    T: ?Sized + Serialize,
+{
+    writer.write_all(to_bytes(value)?.as_slice())?;
+    Ok(())
+}
+
+/// message level serializer, only accepts structs
+pub struct Serializer<'a> {
+    out: &'a mut Vec<u8>,
+}
+
+impl<'a> Serializer<'a> {
+    /// create a serializer appending to `out`
+    pub fn new(out: &'a mut Vec<u8>) -> Self {
+        Serializer { out }
+    }
+
+    fn unsupported<T>(&self) -> Result<T> {
+        Err(SerdeError::Unsupported("top-level value must be a struct"))
+    }
+}
+
+macro_rules! top_level_unsupported {
+    ($($method:ident($($arg:ty),*)),*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<()> {
+                self.unsupported()
+            }
+        )*
+    };
+}
+
+impl<'a> ser::Serializer for Serializer<'a> {
+    type Ok = ();
+    type Error = SerdeError;
+    type SerializeSeq = Impossible<(), SerdeError>;
+    type SerializeTuple = Impossible<(), SerdeError>;
+    type SerializeTupleStruct = Impossible<(), SerdeError>;
+    type SerializeTupleVariant = Impossible<(), SerdeError>;
+    type SerializeMap = Impossible<(), SerdeError>;
+    type SerializeStruct = StructSerializer<'a>;
+    type SerializeStructVariant = Impossible<(), SerdeError>;
+
+    top_level_unsupported!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_none(),
+        serialize_unit(),
+        serialize_unit_variant(&'static str, u32, &'static str)
+    );
+
+    fn serialize_some<T>(self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T>(self, _: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.unsupported()
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq> {
+        self.unsupported()
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple> {
+        self.unsupported()
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.unsupported()
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        self.unsupported()
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap> {
+        self.unsupported()
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeStruct> {
+        Ok(StructSerializer::new(self.out, None, None))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        self.unsupported()
+    }
+}
+
+#[inline]
+fn write_tag(field: u64, wire_type: WireType, out: &mut Vec<u8>) -> Result<()> {
+    write_uvarint((field << 3) | (wire_type as u64), out)?;
+    Ok(())
+}
+
+#[inline]
+fn write_len(field: u64, data: &[u8], out: &mut Vec<u8>) -> Result<()> {
+    write_tag(field, WireType::LEN, out)?;
+    write_uvarint(data.len() as u64, out)?;
+    out.extend_from_slice(data);
+    Ok(())
+}
+
+/// number of the `index`th field (1-based) of a struct, rejects 0 and numbers used before
+pub(crate) fn field_number(key: &str, index: u64, used: &mut Vec<u64>) -> Result<u64> {
+    let field = key.parse::<u64>().unwrap_or(index);
+    if field == 0 {
+        return Err(SerdeError::InvalidFieldNumber(key.to_string(), field));
+    }
+    if used.contains(&field) {
+        return Err(SerdeError::DuplicateFieldNumber(key.to_string(), field));
+    }
+    used.push(field);
+    Ok(field)
+}
+
+/// writes the fields of a struct, optionally wrapped as a nested message
+pub struct StructSerializer<'a> {
+    out: &'a mut Vec<u8>,
+    field: Option<u64>,
+    variant: Option<u64>,
+    buf: Vec<u8>,
+    index: u64,
+    used: Vec<u64>,
+}
+
+impl<'a> StructSerializer<'a> {
+    fn new(out: &'a mut Vec<u8>, field: Option<u64>, variant: Option<u64>) -> Self {
+        StructSerializer {
+            out,
+            field,
+            variant,
+            buf: Vec::new(),
+            index: 0,
+            used: Vec::new(),
+        }
+    }
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.index += 1;
+        let field = field_number(key, self.index, &mut self.used)?;
+        value.serialize(FieldSerializer::new(field, &mut self.buf))
+    }
+
+    /// a skipped field still takes its number
+    fn skip_field(&mut self, key: &'static str) -> Result<()> {
+        self.index += 1;
+        field_number(key, self.index, &mut self.used)?;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        let mut msg = self.buf;
+        if let Some(variant) = self.variant {
+            let mut wrapped = Vec::new();
+            write_len(variant, msg.as_slice(), &mut wrapped)?;
+            msg = wrapped;
+        }
+        match self.field {
+            Some(field) => write_len(field, msg.as_slice(), self.out),
+            None => {
+                self.out.extend_from_slice(msg.as_slice());
+                Ok(())
+            }
+        }
+    }
+}
+
+impl ser::SerializeStruct for StructSerializer<'_> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        StructSerializer::serialize_field(self, key, value)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<()> {
+        StructSerializer::skip_field(self, key)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for StructSerializer<'_> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        StructSerializer::serialize_field(self, key, value)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<()> {
+        StructSerializer::skip_field(self, key)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+/// serializes one value of a field, including its tag
+struct FieldSerializer<'a> {
+    field: u64,
+    out: &'a mut Vec<u8>,
+    repeated: bool,
+}
+
+impl<'a> FieldSerializer<'a> {
+    fn new(field: u64, out: &'a mut Vec<u8>) -> Self {
+        FieldSerializer {
+            field,
+            out,
+            repeated: false,
+        }
+    }
+
+    fn varint(self, v: u64) -> Result<()> {
+        write_tag(self.field, WireType::VARINT, self.out)?;
+        write_uvarint(v, self.out)?;
+        Ok(())
+    }
+
+    fn repeated(self) -> Result<RepeatedSerializer<'a>> {
+        if self.repeated {
+            return Err(SerdeError::Unsupported("nested repeated field"));
+        }
+        Ok(RepeatedSerializer {
+            field: self.field,
+            out: self.out,
+        })
+    }
+}
+
+impl<'a> ser::Serializer for FieldSerializer<'a> {
+    type Ok = ();
+    type Error = SerdeError;
+    type SerializeSeq = RepeatedSerializer<'a>;
+    type SerializeTuple = RepeatedSerializer<'a>;
+    type SerializeTupleStruct = RepeatedSerializer<'a>;
+    type SerializeTupleVariant = Impossible<(), SerdeError>;
+    type SerializeMap = MapSerializer<'a>;
+    type SerializeStruct = StructSerializer<'a>;
+    type SerializeStructVariant = StructSerializer<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.varint(v as u64)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.varint(v as i64 as u64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.varint(v as i64 as u64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.varint(v as i64 as u64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.varint(v as u64)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.varint(v as u64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.varint(v as u64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.varint(v as u64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.varint(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        write_tag(self.field, WireType::I32, self.out)?;
+        write_float(v, self.out)?;
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        write_tag(self.field, WireType::I64, self.out)?;
+        write_double(v, self.out)?;
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.varint(v as u64)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        write_len(self.field, v.as_bytes(), self.out)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        write_len(self.field, v, self.out)
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(self, _: &'static str, index: u32, _: &'static str) -> Result<()> {
+        self.varint(index as u64)
+    }
+
+    fn serialize_newtype_struct<T>(self, _: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _: &'static str,
+        index: u32,
+        _: &'static str,
+        value: &T,
+    ) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut msg = Vec::new();
+        value.serialize(FieldSerializer::new(index as u64 + 1, &mut msg))?;
+        write_len(self.field, msg.as_slice(), self.out)
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq> {
+        self.repeated()
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple> {
+        self.repeated()
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.repeated()
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(SerdeError::Unsupported("tuple variant"))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap> {
+        if self.repeated {
+            return Err(SerdeError::Unsupported("nested repeated field"));
+        }
+        Ok(MapSerializer {
+            field: self.field,
+            out: self.out,
+            entry: Vec::new(),
+        })
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeStruct> {
+        Ok(StructSerializer::new(self.out, Some(self.field), None))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        index: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Ok(StructSerializer::new(
+            self.out,
+            Some(self.field),
+            Some(index as u64 + 1),
+        ))
+    }
+}
+
+/// writes every element with the tag of the field
+pub struct RepeatedSerializer<'a> {
+    field: u64,
+    out: &'a mut Vec<u8>,
+}
+
+impl RepeatedSerializer<'_> {
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(FieldSerializer {
+            field: self.field,
+            out: self.out,
+            repeated: true,
+        })
+    }
+}
+
+macro_rules! impl_repeated_serializer {
+    ($($t:ident => $method:ident),*) => {
+        $(
+            impl ser::$t for RepeatedSerializer<'_> {
+                type Ok = ();
+                type Error = SerdeError;
+
+                fn $method<T>(&mut self, value: &T) -> Result<()>
+                where
+                    T: ?Sized + Serialize,
+                {
+                    self.serialize_element(value)
+                }
+
+                fn end(self) -> Result<()> {
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_repeated_serializer!(
+    SerializeSeq => serialize_element,
+    SerializeTuple => serialize_element,
+    SerializeTupleStruct => serialize_field
+);
+
+/// writes every entry as a `{1: key, 2: value}` message
+pub struct MapSerializer<'a> {
+    field: u64,
+    out: &'a mut Vec<u8>,
+    entry: Vec<u8>,
+}
+
+impl ser::SerializeMap for MapSerializer<'_> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.entry.clear();
+        key.serialize(FieldSerializer::new(1, &mut self.entry))
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(FieldSerializer::new(2, &mut self.entry))?;
+        write_len(self.field, self.entry.as_slice(), self.out)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -1,27 +1,56 @@
-use crate::buffer::Reader;
-use crate::error::DecodeError;
-use anyhow::Result;
-use std::io::Write;
+use crate::buffer::{BufMut, Reader};
+use crate::error::{DecodeError, EncodeError};
+use alloc::vec::Vec;
 
 pub const MAX_VARINT_LENGTH: usize = 10;
 
+/// gets the number of bytes [`write_uvarint`] writes for `x`
+///
+/// example
+/// ```
+/// use protobuf_lite::varint::{encode_uvarint, uvarint_len};
+/// fn main() {
+///     assert_eq!(uvarint_len(150), 2);
+///     assert_eq!(uvarint_len(u64::MAX), encode_uvarint(u64::MAX).len());
+/// }
+/// ```
 #[inline]
-pub fn write_uvarint<T>(mut x: u64, buf: &mut T) -> Result<()>
+pub const fn uvarint_len(x: u64) -> usize {
+    // every byte carries 7 bits, zero still takes one byte
+    let bits = 64 - (x | 1).leading_zeros() as usize;
+    bits.div_ceil(7)
+}
+
+/// gets the number of bytes [`write_varint`] writes for `x`
+#[inline]
+pub const fn varint_len(x: i64) -> usize {
+    let mut ux = (x as u64) << 1;
+    if x < 0 {
+        ux = !ux;
+    }
+    uvarint_len(ux)
+}
+
+#[inline]
+pub fn write_uvarint<T>(mut x: u64, buf: &mut T) -> Result<(), EncodeError>
 where
-    T: Write,
+    T: BufMut + ?Sized,
 {
+    let mut b = [0u8; MAX_VARINT_LENGTH];
+    let mut n = 0;
     while x >= 0x80 {
-        buf.write_all(&[x as u8 | 0x80])?;
+        b[n] = x as u8 | 0x80;
         x >>= 7;
+        n += 1;
     }
-    buf.write_all(&[x as u8])?;
-    Ok(())
+    b[n] = x as u8;
+    buf.put_slice(&b[..n + 1])
 }
 
 #[inline]
-pub fn write_varint<T>(x: i64, buf: &mut T) -> Result<()>
+pub fn write_varint<T>(x: i64, buf: &mut T) -> Result<(), EncodeError>
 where
-    T: Write,
+    T: BufMut + ?Sized,
 {
     let mut ux = (x as u64) << 1;
     if x < 0 {
@@ -46,37 +75,149 @@ pub fn encode_varint(x: i64) -> Vec<u8> {
 }
 
 #[inline]
-pub fn read_uvarint<T>(buf: &mut Reader<T>) -> Result<u64>
+pub fn read_uvarint<T>(buf: &mut Reader<T>) -> Result<u64, DecodeError>
 where
     T: AsRef<[u8]>,
 {
-    let mut x: u64 = 0;
-    let mut shift = 0;
-    if buf.is_end() {
-        return Err(DecodeError::EOF.into());
+    let (x, n) = decode_uvarint(buf.remaining_slice())?;
+    buf.skip(n)?;
+    Ok(x)
+}
+
+/// decode a varint from the beginning of a slice and return it with the number of bytes read
+///
+/// slices with at least [`MAX_VARINT_LENGTH`] bytes are decoded without per-byte bounds checks
+///
+/// example
+/// ```
+/// use protobuf_lite::varint::decode_uvarint;
+/// fn main() {
+///     assert_eq!(decode_uvarint(&[0x96, 0x01, 0xff]).unwrap(), (150, 2));
+///     assert!(decode_uvarint(&[0x96]).is_err());
+/// }
+/// ```
+#[inline]
+pub fn decode_uvarint(buf: &[u8]) -> Result<(u64, usize), DecodeError> {
+    match buf.first_chunk::<MAX_VARINT_LENGTH>() {
+        Some(bytes) => decode_uvarint_unrolled(bytes),
+        None => decode_uvarint_slow(buf),
     }
-    loop {
-        match buf.read_byte() {
-            Ok(v) => {
-                let b = v as u64;
-                x |= (b & 0x7F) << shift;
-                shift += 7;
-                if (b & 0x80) == 0 {
-                    return Ok(x);
-                }
-                if shift >= 64 {
-                    return Err(DecodeError::OverFlow64Bit.into());
-                }
-            }
-            Err(_) => {
-                return Err(DecodeError::UnexpectedEof.into());
+}
+
+#[inline(always)]
+fn decode_uvarint_unrolled(bytes: &[u8; MAX_VARINT_LENGTH]) -> Result<(u64, usize), DecodeError> {
+    // single byte values are the most common, mostly tags and small numbers
+    let b = bytes[0];
+    if b < 0x80 {
+        return Ok((b as u64, 1));
+    }
+    let mut x = (b & 0x7f) as u64;
+    macro_rules! step {
+        ($i:expr) => {
+            let b = bytes[$i];
+            x |= ((b & 0x7f) as u64) << (7 * $i);
+            if b < 0x80 {
+                return Ok((x, $i + 1));
             }
+        };
+    }
+    step!(1);
+    step!(2);
+    step!(3);
+    step!(4);
+    step!(5);
+    step!(6);
+    step!(7);
+    step!(8);
+    step!(9);
+    Err(DecodeError::OverFlow64Bit)
+}
+
+fn decode_uvarint_slow(buf: &[u8]) -> Result<(u64, usize), DecodeError> {
+    if buf.is_empty() {
+        return Err(DecodeError::EOF);
+    }
+    let mut x: u64 = 0;
+    let mut shift = 0;
+    for (i, &b) in buf.iter().enumerate() {
+        x |= ((b & 0x7f) as u64) << shift;
+        shift += 7;
+        if b < 0x80 {
+            return Ok((x, i + 1));
+        }
+        if shift >= 64 {
+            return Err(DecodeError::OverFlow64Bit);
         }
     }
+    Err(DecodeError::UnexpectedEof)
+}
+
+/// like [`decode_uvarint`], but only canonical encodings are accepted
+///
+/// a 10th byte with bits beyond bit 63 is rejected with [`DecodeError::OverFlow64Bit`] and
+/// encodings with trailing zero groups such as `0x80 0x00` with [`DecodeError::OverlongVarint`]
+///
+/// example
+/// ```
+/// use protobuf_lite::error::DecodeError;
+/// use protobuf_lite::varint::{decode_uvarint, decode_uvarint_strict};
+/// fn main() {
+///     assert_eq!(decode_uvarint(&[0x80, 0x00]).unwrap(), (0, 2));
+///     assert!(matches!(
+///         decode_uvarint_strict(&[0x80, 0x00]),
+///         Err(DecodeError::OverlongVarint)
+///     ));
+/// }
+/// ```
+#[inline]
+pub fn decode_uvarint_strict(buf: &[u8]) -> Result<(u64, usize), DecodeError> {
+    let (x, n) = decode_uvarint(buf)?;
+    // the last byte of a canonical encoding carries the highest set bits, so it is never zero
+    // except for zero itself, and only bit 63 is left for the 10th byte
+    match buf[n - 1] {
+        0 if n > 1 => Err(DecodeError::OverlongVarint),
+        b if n == MAX_VARINT_LENGTH && b > 1 => Err(DecodeError::OverFlow64Bit),
+        _ => Ok((x, n)),
+    }
+}
+
+/// like [`read_uvarint`], but only canonical encodings are accepted, see [`decode_uvarint_strict`]
+#[inline]
+pub fn read_uvarint_strict<T>(buf: &mut Reader<T>) -> Result<u64, DecodeError>
+where
+    T: AsRef<[u8]>,
+{
+    let (x, n) = decode_uvarint_strict(buf.remaining_slice())?;
+    buf.skip(n)?;
+    Ok(x)
+}
+
+/// read a varint which must fit into 32 bits, larger values return [`DecodeError::OverFlow32Bit`]
+#[inline]
+pub fn read_uvarint32<T>(buf: &mut Reader<T>) -> Result<u32, DecodeError>
+where
+    T: AsRef<[u8]>,
+{
+    let (x, n) = decode_uvarint(buf.remaining_slice())?;
+    let x = u32::try_from(x).map_err(|_| DecodeError::OverFlow32Bit)?;
+    buf.skip(n)?;
+    Ok(x)
+}
+
+/// like [`read_uvarint32`], but only canonical encodings are accepted
+#[inline]
+pub fn read_uvarint32_strict<T>(buf: &mut Reader<T>) -> Result<u32, DecodeError>
+where
+    T: AsRef<[u8]>,
+{
+    let (x, n) = decode_uvarint_strict(buf.remaining_slice())?;
+    let x = u32::try_from(x).map_err(|_| DecodeError::OverFlow32Bit)?;
+    buf.skip(n)?;
+    Ok(x)
 }
 
 #[inline]
-pub fn read_varint<T>(buf: &mut Reader<T>) -> Result<i64>
+pub fn read_varint<T>(buf: &mut Reader<T>) -> Result<i64, DecodeError>
 where
     T: AsRef<[u8]>,
 {